@@ -3649,7 +3649,7 @@ mod tests {
       let mut entropy = [0; 16];
       rand::thread_rng().fill_bytes(&mut entropy);
       let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
-      crate::subcommand::wallet::initialize("ord".into(), &context.options, mnemonic.to_seed(""))
+      crate::subcommand::wallet::initialize("ord".into(), &context.options, mnemonic.to_seed(""), crate::subcommand::wallet::AddressType::Bech32m, false)
         .unwrap();
       context.rpc_server.mine_blocks(1);
       assert_regex_match!(
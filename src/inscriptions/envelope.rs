@@ -267,20 +267,23 @@ mod tests {
   use super::*;
 
   fn parse(witnesses: &[Witness]) -> Vec<ParsedEnvelope> {
-    ParsedEnvelope::from_transaction(&Transaction {
-      version: 2,
-      lock_time: LockTime::ZERO,
-      input: witnesses
-        .iter()
-        .map(|witness| TxIn {
-          previous_output: OutPoint::null(),
-          script_sig: ScriptBuf::new(),
-          sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
-          witness: witness.clone(),
-        })
-        .collect(),
-      output: Vec::new(),
-    })
+    ParsedEnvelope::from_transaction(
+      &Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: witnesses
+          .iter()
+          .map(|witness| TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: witness.clone(),
+          })
+          .collect(),
+        output: Vec::new(),
+      },
+      false,
+    )
   }
 
   #[test]
@@ -131,6 +131,33 @@ impl Inscription {
     })
   }
 
+  pub(crate) fn from_content(
+    chain: Chain,
+    content: Vec<u8>,
+    parent: Option<InscriptionId>,
+    pointer: Option<u64>,
+    metaprotocol: String,
+    metadata: Option<Vec<u8>>,
+    utxo: Option<OutPoint>,
+  ) -> Result<Self, Error> {
+    if let Some(limit) = chain.inscription_content_size_limit() {
+      let len = content.len();
+      if len > limit {
+        bail!("content size of {len} bytes exceeds {limit} byte limit for {chain} inscriptions");
+      }
+    }
+
+    Ok(Self {
+      body: Some(content),
+      metadata,
+      metaprotocol: Some(metaprotocol.into_bytes()),
+      parent: parent.map(|id| id.value()),
+      pointer: pointer.map(Self::pointer_value),
+      utxo,
+      ..Default::default()
+    })
+  }
+
   pub(crate) fn pointer_value(pointer: u64) -> Vec<u8> {
     let mut bytes = pointer.to_le_bytes().to_vec();
 
@@ -764,18 +791,33 @@ mod tests {
     write!(file, "foo").unwrap();
 
     let inscription =
-      Inscription::from_file(Chain::Mainnet, file.path(), None, None, None, None, false).unwrap();
+      Inscription::from_file(
+        Chain::Mainnet,
+        None,
+        file.path(),
+        None,
+        None,
+        None,
+        None,
+        false,
+        false,
+        None,
+      )
+      .unwrap();
 
     assert_eq!(inscription.pointer, None);
 
     let inscription = Inscription::from_file(
       Chain::Mainnet,
+      None,
       file.path(),
       None,
       Some(0),
       None,
       None,
       false,
+      false,
+      None,
     )
     .unwrap();
 
@@ -783,12 +825,15 @@ mod tests {
 
     let inscription = Inscription::from_file(
       Chain::Mainnet,
+      None,
       file.path(),
       None,
       Some(1),
       None,
       None,
       false,
+      false,
+      None,
     )
     .unwrap();
 
@@ -796,12 +841,15 @@ mod tests {
 
     let inscription = Inscription::from_file(
       Chain::Mainnet,
+      None,
       file.path(),
       None,
       Some(256),
       None,
       None,
       false,
+      false,
+      None,
     )
     .unwrap();
 
@@ -871,4 +919,25 @@ mod tests {
     }
     .hidden());
   }
+
+  #[test]
+  fn metaprotocol_only_inscription_has_empty_body() {
+    let inscription = Inscription::from_content(
+      Chain::Mainnet,
+      Vec::new(),
+      None,
+      None,
+      "brc-20".into(),
+      None,
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(inscription.content_type(), None);
+    assert_eq!(inscription.body(), Some([].as_slice()));
+    assert_eq!(
+      inscription.to_witness(),
+      envelope(&[b"ord", &[7], b"brc-20", &[]]),
+    );
+  }
 }
@@ -785,7 +785,7 @@ mod tests {
   }
 
   #[test]
-  fn index_runes_only_returns_true_if_index_runes_flag_is_passed_and_not_on_mainnnet() {
+  fn index_runes_only_returns_true_if_index_runes_flag_is_passed() {
     assert!(Arguments::try_parse_from([
       "ord",
       "--chain=signet",
@@ -797,7 +797,7 @@ mod tests {
     .options
     .index_runes());
     assert!(
-      !Arguments::try_parse_from(["ord", "--index-runes", "index", "update"])
+      Arguments::try_parse_from(["ord", "--index-runes", "index", "update"])
         .unwrap()
         .options
         .index_runes()
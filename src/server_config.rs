@@ -6,6 +6,9 @@ pub(crate) struct ServerConfig {
   pub(crate) csp_origin: Option<String>,
   pub(crate) decompress: bool,
   pub(crate) domain: Option<String>,
+  pub(crate) idempotency_ttl: Duration,
   pub(crate) index_sats: bool,
+  pub(crate) inscribe_file_base: Option<PathBuf>,
   pub(crate) is_json_api_enabled: bool,
+  pub(crate) strict_address_network: bool,
 }
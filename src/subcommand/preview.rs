@@ -106,41 +106,71 @@ impl Preview {
           subcommand: Subcommand::Wallet(super::wallet::Wallet {
             name: "ord".into(),
             subcommand: super::wallet::Subcommand::Inscribe(super::wallet::inscribe::Inscribe {
+              allow_change_reuse: false,
+              allow_spend_runic: false,
+              allow_unbound: false,
               batch: None,
+              broadcast_retries: 3,
               cbor_metadata: None,
               change: None,
               coin_control: false,
               commit_fee_rate: None,
               commit_input: Vec::new(),
+              commit_locktime: None,
               commit_only: false,
+              commit_output_margin: None,
+              commit_tx_version: 2,
               commit_vsize: None,
               commitment: None,
               compress: false,
+              content: None,
+              dedupe: false,
+              deny_change_reuse: false,
               destination: None,
               dump: false,
+              dump_json: None,
               dry_run: false,
+              estimate: false,
               fee_rate: FeeRate::try_from(1.0).unwrap(),
+              fee_utxo_only: false,
               file: Some(file),
+              finalize_reveal_psbt: None,
+              handoff: None,
               json_metadata: None,
               key: None,
               metaprotocol: None,
               next_batch: None,
               next_file: None,
+              min_commit_confirmations: 0,
               no_backup: true,
               no_broadcast: false,
               no_limit: false,
+              no_rbf: false,
               no_wallet: false,
               parent: None,
               parent_satpoint: None,
+              parent_value: None,
               parent_destination: None,
+              pointer: None,
               postage: Some(TARGET_POSTAGE),
+              prefer_utxo: Vec::new(),
+              preserve_sat_offset: false,
+              preview_envelope: false,
+              psbt_package: false,
               reinscribe: false,
+              reveal_change: None,
               reveal_fee: None,
               reveal_input: Vec::new(),
+              reveal_parent_internal_key: None,
+              reveal_parent_psbt: false,
+              reveal_tx_version: 2,
               satpoint: None,
               sat: None,
+              sat_range: None,
+              skip_index_update: false,
               skip_pointer_for_none: false,
               utxo: Vec::new(),
+              weight_warning_percent: 90,
             }),
           }),
         }
@@ -157,41 +187,71 @@ impl Preview {
           subcommand: Subcommand::Wallet(super::wallet::Wallet {
             name: "ord".into(),
             subcommand: super::wallet::Subcommand::Inscribe(super::wallet::inscribe::Inscribe {
+              allow_change_reuse: false,
+              allow_spend_runic: false,
+              allow_unbound: false,
               batch: Some(batch),
+              broadcast_retries: 3,
               cbor_metadata: None,
               change: None,
               coin_control: false,
               commit_fee_rate: None,
               commit_input: Vec::new(),
+              commit_locktime: None,
               commit_only: false,
+              commit_output_margin: None,
+              commit_tx_version: 2,
               commit_vsize: None,
               commitment: None,
               compress: false,
+              content: None,
+              dedupe: false,
+              deny_change_reuse: false,
               destination: None,
               dump: false,
+              dump_json: None,
               dry_run: false,
+              estimate: false,
               fee_rate: FeeRate::try_from(1.0).unwrap(),
+              fee_utxo_only: false,
               file: None,
+              finalize_reveal_psbt: None,
+              handoff: None,
               json_metadata: None,
               key: None,
               metaprotocol: None,
               next_batch: None,
               next_file: None,
+              min_commit_confirmations: 0,
               no_backup: true,
               no_broadcast: false,
               no_limit: false,
+              no_rbf: false,
               no_wallet: false,
               parent: None,
               parent_destination: None,
               parent_satpoint: None,
+              parent_value: None,
+              pointer: None,
               postage: Some(TARGET_POSTAGE),
+              prefer_utxo: Vec::new(),
+              preserve_sat_offset: false,
+              preview_envelope: false,
+              psbt_package: false,
               reinscribe: false,
+              reveal_change: None,
               reveal_fee: None,
               reveal_input: Vec::new(),
+              reveal_parent_internal_key: None,
+              reveal_parent_psbt: false,
+              reveal_tx_version: 2,
               satpoint: None,
               sat: None,
+              sat_range: None,
+              skip_index_update: false,
               skip_pointer_for_none: false,
               utxo: Vec::new(),
+              weight_warning_percent: 90,
             }),
           }),
         }
@@ -239,6 +239,22 @@ pub(crate) struct Server {
   pub(crate) decompress: bool,
   #[arg(long, alias = "nosync", help = "Do not update the index.")]
   no_sync: bool,
+  #[arg(
+    long,
+    default_value = "86400",
+    help = "Return cached /inscribe results for repeated idempotency keys for <IDEMPOTENCY_TTL> seconds."
+  )]
+  idempotency_ttl: u64,
+  #[arg(
+    long,
+    help = "Allow /inscribe to read `file://` URLs and absolute paths inside <INSCRIBE_FILE_BASE>."
+  )]
+  inscribe_file_base: Option<PathBuf>,
+  #[arg(
+    long,
+    help = "Reject /inscribe destinations that are not valid for the active chain."
+  )]
+  strict_address_network: bool,
 }
 
 impl Server {
@@ -266,9 +282,12 @@ impl Server {
         chain: options.chain(),
         csp_origin: self.csp_origin.clone(),
         domain: acme_domains.first().cloned(),
+        idempotency_ttl: Duration::from_secs(self.idempotency_ttl),
         index_sats: index.has_sat_index(),
+        inscribe_file_base: self.inscribe_file_base.clone(),
         is_json_api_enabled: self.enable_json_api,
         decompress: self.decompress,
+        strict_address_network: self.strict_address_network,
       });
 
       let router = Router::new()
@@ -1674,7 +1693,7 @@ impl Server {
     task::block_in_place(|| {
       log::info!("POST /inscribe");
 
-      match Inscribe::inscribe_for_server(data.clone(), server_config.chain, &index) {
+      match Inscribe::inscribe_for_server(data.clone(), &server_config, &index) {
         Ok(result) => Ok(Json(result).into_response()),
         Err(str) => Err(ServerError::BadRequest(format!("error: {str}"))),
       }
@@ -99,6 +99,14 @@ pub(crate) fn get_unspent_outputs(
   client: &Client,
   index: &Index,
 ) -> Result<BTreeMap<OutPoint, Amount>> {
+  let utxos = get_unspent_outputs_unchecked(client)?;
+
+  index.check_sync(&utxos)?;
+
+  Ok(utxos)
+}
+
+pub(crate) fn get_unspent_outputs_unchecked(client: &Client) -> Result<BTreeMap<OutPoint, Amount>> {
   let mut utxos = BTreeMap::new();
   utxos.extend(
     client
@@ -125,8 +133,6 @@ pub(crate) fn get_unspent_outputs(
     );
   }
 
-  index.check_sync(&utxos)?;
-
   Ok(utxos)
 }
 
@@ -1,11 +1,12 @@
 use {
   self::batch::{Batch, BatchEntry, Batchfile, Mode},
   super::*,
-  crate::subcommand::wallet::transaction_builder::Target,
+  crate::{server_config::ServerConfig, subcommand::wallet::transaction_builder::Target},
   base64::{Engine as _, engine::general_purpose},
   bitcoin::{
     blockdata::{opcodes, script},
     key::PrivateKey,
+    hashes::{hash160, sha256},
     key::{TapTweak, TweakedKeyPair, TweakedPublicKey, UntweakedKeyPair},
     policy::MAX_STANDARD_TX_WEIGHT,
     psbt::Psbt,
@@ -25,40 +26,217 @@ use {
 
 mod batch;
 
+const MAX_FETCH_HEADERS_SIZE: usize = 8192;
+
+const MAX_METADATA_FETCH_SIZE: u64 = MAX_STANDARD_TX_WEIGHT as u64;
+
+const MAX_INSCRIPTION_FETCH_SIZE: u64 = MAX_STANDARD_TX_WEIGHT as u64;
+
+const IDEMPOTENCY_LOCK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct InscriptionInfo {
   pub id: InscriptionId,
   pub location: SatPoint,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sat: Option<Sat>,
 }
 
 fn is_zero(n: &u64) -> bool {
   *n == 0
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Output {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub commit: Option<Txid>,
   #[serde(skip_serializing_if = "Option::is_none")]
+  pub commit_address: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub commit_fee: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub commit_hex: Option<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub commit_inputs: Vec<OutPoint>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub commit_psbt: Option<String>,
-  #[serde(skip_serializing_if = "Vec::is_empty")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub content_hashes: Vec<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub inscriptions: Vec<InscriptionInfo>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub message: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub parent: Option<InscriptionId>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub psbt_package: Vec<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub recovery_descriptor: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub recovery_key_fingerprint: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub reveal: Option<Txid>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub reveal_fee: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub reveal_hex: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub reveal_psbt: Option<String>,
-  #[serde(skip_serializing_if = "is_zero")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub reveal_vsize: Option<u64>,
+  #[serde(default, skip_serializing_if = "is_zero")]
   pub total_fees: u64,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnvelopePreview {
+  pub inscriptions: Vec<EnvelopeSummary>,
+  pub reveal_script: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnvelopeSummary {
+  pub body_length: Option<usize>,
+  pub content_type: Option<String>,
+  pub metadata_length: Option<usize>,
+  pub metaprotocol: Option<String>,
+  pub parent: Option<InscriptionId>,
+  pub pointer: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RecoveryDump {
+  pub commit_address: Option<String>,
+  pub commit_hex: Option<String>,
+  pub inscriptions: Vec<InscriptionId>,
+  pub key: String,
+  pub recovery_descriptor: String,
+  pub reveal_hex: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Handoff {
+  pub commit_address: Address<NetworkUnchecked>,
+  pub content_hashes: Vec<String>,
+  pub key: String,
+  pub postage: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IdempotencyRecord {
+  created: u64,
+  output: Output,
+  request: String,
+}
+
+// Marks an idempotency key as in flight, so that a concurrent request with the
+// same key fails instead of inscribing a second time. Removed on drop. The
+// lock holds the time it was taken, so that a lock left behind by a process
+// that died is ignored once it is older than IDEMPOTENCY_LOCK_TIMEOUT.
+struct IdempotencyLock(PathBuf);
+
+impl IdempotencyLock {
+  fn acquire(path: PathBuf, idempotency_key: &str) -> Result<Self> {
+    let mut stale_removed = false;
+
+    loop {
+      match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(mut file) => {
+          file.write_all(Inscribe::now().to_string().as_bytes())?;
+          return Ok(Self(path));
+        }
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+          if stale_removed || !Self::is_stale(&path) {
+            bail!("a request with idempotency key {idempotency_key} is already in progress");
+          }
+
+          eprintln!("removing stale idempotency lock `{}`", path.display());
+          fs::remove_file(&path).ok();
+          stale_removed = true;
+        }
+        Err(err) => bail!("failed to create idempotency lock `{}`: {err}", path.display()),
+      }
+    }
+  }
+
+  // Locks whose time can't be read fall back to the file's modification time.
+  fn is_stale(path: &Path) -> bool {
+    let created = fs::read_to_string(path)
+      .ok()
+      .and_then(|created| created.trim().parse::<u64>().ok())
+      .or_else(|| {
+        fs::metadata(path)
+          .and_then(|metadata| metadata.modified())
+          .ok()
+          .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+          .map(|modified| modified.as_secs())
+      });
+
+    match created {
+      Some(created) => Inscribe::now().saturating_sub(created) > IDEMPOTENCY_LOCK_TIMEOUT.as_secs(),
+      None => false,
+    }
+  }
+}
+
+impl Drop for IdempotencyLock {
+  fn drop(&mut self) {
+    fs::remove_file(&self.0).ok();
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum FeeSelection {
+  #[default]
+  AsListed,
+  LargestFirst,
+  SmallestFirst,
+}
+
+impl FromStr for FeeSelection {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s {
+      "as-listed" => Ok(Self::AsListed),
+      "largest-first" => Ok(Self::LargestFirst),
+      "smallest-first" => Ok(Self::SmallestFirst),
+      _ => bail!("invalid fee selection `{s}`, expected `largest-first`, `smallest-first` or `as-listed`"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SatInterval {
+  start: Sat,
+  end: Sat,
+}
+
+impl FromStr for SatInterval {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let (start, end) = s
+      .split_once('-')
+      .ok_or_else(|| anyhow!("sat range must be of the form <START>-<END>"))?;
+
+    let start = start.parse::<Sat>()?;
+    let end = end.parse::<Sat>()?;
+
+    if start >= end {
+      bail!("sat range start must be less than end");
+    }
+
+    Ok(Self { start, end })
+  }
+}
+
+impl Display for SatInterval {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}-{}", self.start, self.end)
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -73,20 +251,20 @@ pub(crate) struct ParentInfo {
 #[clap(
   group = ArgGroup::new("source")
       .required(true)
-      .args(&["file", "batch"]),
+      .args(&["file", "batch", "content", "finalize_reveal_psbt"]),
 )]
 pub(crate) struct Inscribe {
   #[arg(
     long,
     help = "Inscribe multiple inscriptions defined in a yaml <BATCH_FILE>.",
     conflicts_with_all = &[
-      "cbor_metadata", "destination", "file", "json_metadata", "metaprotocol", "parent", "postage", "reinscribe", "satpoint"
+      "destination", "file", "metaprotocol", "parent", "pointer", "postage", "reinscribe", "sat_range", "satpoint"
     ]
   )]
   pub(crate) batch: Option<PathBuf>,
   #[arg(
     long,
-    help = "Include CBOR in file at <METADATA> as inscription metadata",
+    help = "Include CBOR in file at <METADATA> as inscription metadata. <METADATA> may also be `-` to read from stdin or an http(s) URL",
     conflicts_with = "json_metadata"
   )]
   pub(crate) cbor_metadata: Option<PathBuf>,
@@ -95,6 +273,33 @@ pub(crate) struct Inscribe {
     help = "Consider spending outpoint <UTXO>, even if it is unconfirmed or contains inscriptions"
   )]
   pub(crate) utxo: Vec<OutPoint>,
+  #[arg(
+    long,
+    help = "Allow the same change address to be used more than once for commit and reveal change."
+  )]
+  pub(crate) allow_change_reuse: bool,
+  #[arg(
+    long,
+    conflicts_with = "allow_change_reuse",
+    help = "Fail instead of warning when --change and --reveal-change are the same address."
+  )]
+  pub(crate) deny_change_reuse: bool,
+  #[arg(
+    long,
+    help = "Allow spending forced inputs that carry runes. The runes will be burned."
+  )]
+  pub(crate) allow_spend_runic: bool,
+  #[arg(
+    long,
+    help = "Allow `pointer` and `offset` values in <BATCH> and <NEXT_BATCH> that point outside of their inscription's output."
+  )]
+  pub(crate) allow_unbound: bool,
+  #[arg(
+    long,
+    default_value = "3",
+    help = "Retry broadcasting the commit and reveal transactions up to <BROADCAST_RETRIES> times on transient node errors."
+  )]
+  pub(crate) broadcast_retries: u32,
   #[arg(long, help = "Only spend outpoints given with --utxo")]
   pub(crate) coin_control: bool,
   #[arg(long, help = "Send any change output to <CHANGE>.")]
@@ -104,24 +309,74 @@ pub(crate) struct Inscribe {
     help = "Use <COMMIT_FEE_RATE> sats/vbyte for commit transaction.\nDefaults to <FEE_RATE> if unset."
   )]
   pub(crate) commit_fee_rate: Option<FeeRate>,
+  #[arg(
+    long,
+    help = "Set the nLockTime of the commit transaction to <COMMIT_LOCKTIME>."
+  )]
+  pub(crate) commit_locktime: Option<u32>,
+  #[arg(
+    long,
+    help = "Require the commitment output to cover dust, postage and the reveal fee with at least <COMMIT_OUTPUT_MARGIN> to spare. For use with `--commitment`."
+  )]
+  pub(crate) commit_output_margin: Option<Amount>,
+  #[arg(
+    long,
+    default_value = "2",
+    help = "Use transaction version <COMMIT_TX_VERSION> for the commit transaction. Must be 1 or 2."
+  )]
+  pub(crate) commit_tx_version: i32,
   #[arg(long, help = "Compress inscription content with brotli.")]
   pub(crate) compress: bool,
+  #[arg(
+    long,
+    requires = "metaprotocol",
+    help = "Inscribe <CONTENT> with no content type instead of a file. The content may be empty. Requires --metaprotocol."
+  )]
+  pub(crate) content: Option<String>,
+  #[arg(
+    long,
+    help = "Warn about inscriptions in <BATCH> with identical content, and how much weight inscribing them as delegates would save.",
+    requires = "batch"
+  )]
+  pub(crate) dedupe: bool,
   #[arg(long, help = "Send inscription to <DESTINATION>.")]
   pub(crate) destination: Option<Address<NetworkUnchecked>>,
   #[arg(long, help = "Don't sign or broadcast transactions.")]
   pub(crate) dry_run: bool,
+  #[arg(
+    long,
+    conflicts_with = "reveal_parent_psbt",
+    help = "Only estimate commit and reveal fees. Don't sign, broadcast or report transactions."
+  )]
+  pub(crate) estimate: bool,
   #[arg(long, help = "Use fee rate of <FEE_RATE> sats/vB.")]
   pub(crate) fee_rate: FeeRate,
+  #[arg(
+    long,
+    help = "Only spend the inscribed sat's output and the fee UTXOs given with --commit-input in the commit transaction. Fail instead of spending other wallet UTXOs."
+  )]
+  pub(crate) fee_utxo_only: bool,
   #[arg(long, help = "Inscribe sat with contents of <FILE>.")]
   pub(crate) file: Option<PathBuf>,
   #[arg(
     long,
-    help = "Include JSON in file at <METADATA> converted to CBOR as inscription metadata",
+    help = "Finalize <FINALIZE_REVEAL_PSBT>, a reveal PSBT exported with --reveal-parent-psbt whose parent input has been signed externally, and broadcast the reveal transaction."
+  )]
+  pub(crate) finalize_reveal_psbt: Option<String>,
+  #[arg(
+    long,
+    help = "Include JSON in file at <METADATA> converted to CBOR as inscription metadata. <METADATA> may also be `-` to read from stdin or an http(s) URL",
     conflicts_with = "cbor_metadata"
   )]
   pub(crate) json_metadata: Option<PathBuf>,
   #[clap(long, help = "Set inscription metaprotocol to <METAPROTOCOL>.")]
   pub(crate) metaprotocol: Option<String>,
+  #[arg(
+    long,
+    default_value = "0",
+    help = "Require the `--commitment` transaction to have at least <MIN_COMMIT_CONFIRMATIONS> confirmations before building the reveal."
+  )]
+  pub(crate) min_commit_confirmations: u32,
   #[arg(long, alias = "nobackup", help = "Do not back up recovery key.")]
   pub(crate) no_backup: bool,
   #[arg(
@@ -130,19 +385,64 @@ pub(crate) struct Inscribe {
     help = "Do not check that transactions are equal to or below the MAX_STANDARD_TX_WEIGHT of 400,000 weight units. Transactions over this limit are currently nonstandard and will not be relayed by bitcoind in its default configuration. Do not use this flag unless you understand the implications."
   )]
   pub(crate) no_limit: bool,
+  #[arg(
+    long,
+    help = "Don't signal replaceability on the reveal transaction. The commit transaction still signals RBF."
+  )]
+  pub(crate) no_rbf: bool,
   #[clap(long, help = "Make inscription a child of <PARENT>.")]
   pub(crate) parent: Option<InscriptionId>,
   #[clap(long, help = "Address to return parent inscription to.")]
   pub(crate) parent_destination: Option<Address<NetworkUnchecked>>,
   #[clap(long, help = "The satpoint of the parent inscription, in case it isn't confirmed yet.")]
   pub(crate) parent_satpoint: Option<SatPoint>,
+  #[arg(
+    long,
+    requires = "parent_destination",
+    help = "The value of the output holding the parent inscription at --parent-destination. For use with --no-wallet when bitcoind doesn't know the parent transaction yet."
+  )]
+  pub(crate) parent_value: Option<Amount>,
+  #[arg(
+    long,
+    alias = "inscription-pointer",
+    help = "Point inscription at sat <POINTER> within its output, e.g. to co-locate it with an existing inscription."
+  )]
+  pub(crate) pointer: Option<u64>,
   #[arg(
     long,
     help = "Amount of postage to include in the inscription. Default `10000sat`."
   )]
   pub(crate) postage: Option<Amount>,
+  #[arg(
+    long,
+    conflicts_with_all = &["sat", "sat_range", "satpoint"],
+    help = "Try to inscribe on <PREFER_UTXO> before other cardinal wallet UTXOs. May be given more than once, in order of preference."
+  )]
+  pub(crate) prefer_utxo: Vec<OutPoint>,
+  #[arg(
+    long,
+    help = "In `same-sat` mode with a target sat, fail instead of warning if the postage leaves the inscribed sat within a dust-sized distance of the end of its output."
+  )]
+  pub(crate) preserve_sat_offset: bool,
+  #[arg(
+    long,
+    conflicts_with_all = &["commit_only", "commitment", "estimate", "reveal_parent_psbt"],
+    help = "Output the unsigned commit and the reveal, with its commit input signed, as a two-PSBT package instead of signing or broadcasting. The commit inputs must be segwit, so that signing them doesn't change the commit txid the reveal spends."
+  )]
+  pub(crate) psbt_package: bool,
+  #[arg(
+    long,
+    conflicts_with = "finalize_reveal_psbt",
+    help = "Print the reveal script and a summary of each inscription envelope without opening the index or wallet, or building any transactions. Pointers are not offset by the parent output value."
+  )]
+  pub(crate) preview_envelope: bool,
   #[clap(long, help = "Allow reinscription.")]
   pub(crate) reinscribe: bool,
+  #[arg(
+    long,
+    help = "Send the change of the reveal tx to <REVEAL_CHANGE>. For use with `--commitment`."
+  )]
+  pub(crate) reveal_change: Option<Address<NetworkUnchecked>>,
   #[arg(long, help = "Specify the reveal tx fee.")]
   pub(crate) reveal_fee: Option<Amount>,
   #[arg(long, help = "Inscribe <SATPOINT>.")]
@@ -153,26 +453,74 @@ pub(crate) struct Inscribe {
   pub(crate) commit_only: bool,
   #[clap(long, help = "Don't make a commit transaction; just create a reveal tx that reveals the inscription committed to by output <COMMITMENT>. Requires the same --key as was used to make the commitment. Implies --no-backup. This doesn't work if the --key has ever been backed up to the wallet. When using --commitment, the reveal tx will create a change output unless --reveal-fee is set to '0 sats', in which case the whole commitment will go to postage and fees.")]
   pub(crate) commitment: Option<OutPoint>,
+  #[arg(
+    long,
+    help = "With `--commit-only`, once the commit is broadcast, write the key, commit address, inscription content hashes and postage as JSON to <HANDOFF>. With `--commitment`, check that `--key` and the inscription content reproduce the commit address in <HANDOFF> before building the reveal."
+  )]
+  pub(crate) handoff: Option<PathBuf>,
   #[arg(long, help = "Make the change of the reveal tx commit to the contents of multiple inscriptions defined in a yaml <NEXT-BATCH>.")]
   pub(crate) next_batch: Option<PathBuf>,
   #[clap(long, help = "Make the change of the reveal tx commit to the contents of <NEXT-FILE>.")]
   pub(crate) next_file: Option<PathBuf>,
   #[clap(long, help = "Use <REVEAL-INPUT> as an extra input to the reveal tx. For use with `--commitment`.")]
   pub(crate) reveal_input: Vec<OutPoint>,
+  #[arg(
+    long,
+    requires = "reveal_parent_psbt",
+    help = "Set the taproot internal key of the parent input in the exported reveal PSBT to <REVEAL_PARENT_INTERNAL_KEY>."
+  )]
+  pub(crate) reveal_parent_internal_key: Option<XOnlyPublicKey>,
+  #[arg(
+    long,
+    conflicts_with_all = &["commit_only", "no_wallet"],
+    help = "Don't sign the parent input of the reveal transaction with the wallet. Export the reveal as a PSBT with the commit input signed instead, and don't broadcast anything. Finish with --finalize-reveal-psbt."
+  )]
+  pub(crate) reveal_parent_psbt: bool,
+  #[arg(
+    long,
+    default_value = "2",
+    help = "Use transaction version <REVEAL_TX_VERSION> for the reveal transaction. Must be 1 or 2."
+  )]
+  pub(crate) reveal_tx_version: i32,
   #[clap(long, help = "Dump raw hex transactions and recovery keys to standard output.")]
   pub(crate) dump: bool,
+  #[clap(
+    long,
+    conflicts_with_all = &["dry_run", "estimate", "no_wallet", "psbt_package", "reveal_parent_psbt"],
+    help = "Write raw hex transactions, recovery descriptor, key, commit address and inscription ids as JSON to <DUMP_JSON>."
+  )]
+  pub(crate) dump_json: Option<PathBuf>,
   #[clap(long, help = "Do not broadcast any transactions. Implies --dump.")]
   pub(crate) no_broadcast: bool,
   #[clap(long, help = "Use <COMMIT-INPUT> as an extra input to the commit tx. Useful for forcing CPFP.")]
   pub(crate) commit_input: Vec<OutPoint>,
   #[arg(long, help = "Inscribe <SAT>.", conflicts_with = "satpoint")]
   pub(crate) sat: Option<Sat>,
+  #[arg(
+    long,
+    help = "Inscribe the lowest sat in the wallet within <SAT_RANGE>, given as <START>-<END>, with <END> exclusive.",
+    conflicts_with_all = &["sat", "satpoint"]
+  )]
+  pub(crate) sat_range: Option<SatInterval>,
   #[arg(long, help = "Don't use a local wallet. Leave the commit transaction unsigned instead.")]
   pub(crate) no_wallet: bool,
   #[arg(long, help = "Specify the vsize of the commit tx, for when we don't have a local wallet to sign with.")]
   pub(crate) commit_vsize: Option<u64>,
   #[arg(long, help = "Whether to omit pointer from the envelope of blank inscriptions.")]
   pub(crate) skip_pointer_for_none: bool,
+  #[arg(
+    long,
+    requires = "dry_run",
+    help = "Don't update the index before a dry run. Use whatever the index currently has."
+  )]
+  pub(crate) skip_index_update: bool,
+  #[arg(
+    long,
+    default_value = "90",
+    value_parser = clap::value_parser!(u64).range(1..=99),
+    help = "Warn when the reveal transaction weight is above <WEIGHT_WARNING_PERCENT> percent of MAX_STANDARD_TX_WEIGHT."
+  )]
+  pub(crate) weight_warning_percent: u64,
 }
 
 impl Inscribe {
@@ -189,6 +537,16 @@ impl Inscribe {
       return Err(anyhow!("--next-batch and --next-file don't work together"));
     }
 
+    if self.handoff.is_some() && !self.commit_only && self.commitment.is_none() {
+      return Err(anyhow!("--handoff only works with --commit-only or --commitment"));
+    }
+
+    if self.handoff.is_some() && self.commit_only && (self.no_broadcast || self.no_wallet) {
+      return Err(anyhow!(
+        "--handoff is written once the commit is broadcast, so it doesn't work with --no-broadcast or --no-wallet"
+      ));
+    }
+
     if self.commit_only && self.next_batch.is_some() {
       return Err(anyhow!("--commit-only and --next-batch don't work together"));
     }
@@ -201,6 +559,26 @@ impl Inscribe {
       return Err(anyhow!("--reveal-input only works with --commitment"));
     }
 
+    if ![1, 2].contains(&self.commit_tx_version) {
+      return Err(anyhow!("--commit-tx-version must be 1 or 2"));
+    }
+
+    if ![1, 2].contains(&self.reveal_tx_version) {
+      return Err(anyhow!("--reveal-tx-version must be 1 or 2"));
+    }
+
+    if self.commitment.is_none() && self.commit_output_margin.is_some() {
+      return Err(anyhow!("--commit-output-margin only works with --commitment"));
+    }
+
+    if self.commitment.is_none() && self.min_commit_confirmations > 0 {
+      return Err(anyhow!("--min-commit-confirmations only works with --commitment"));
+    }
+
+    if self.reveal_change.is_some() && (self.next_batch.is_some() || self.next_file.is_some()) {
+      return Err(anyhow!("--reveal-change doesn't work with --next-batch or --next-file"));
+    }
+
     let mut no_backup = self.no_backup;
     if self.commit_only || self.commitment.is_some() {
       no_backup = true;
@@ -213,8 +591,105 @@ impl Inscribe {
       dump = true;
     }
 
+    if self.preview_envelope {
+      let chain = options.chain();
+      let postage = self.postage.unwrap_or(TARGET_POSTAGE);
+
+      let inscriptions = match (self.file, self.batch) {
+        (Some(file), None) => vec![Inscription::from_file(
+          chain,
+          None,
+          file,
+          self.parent,
+          self.pointer,
+          self.metaprotocol,
+          metadata,
+          self.compress,
+          self.skip_pointer_for_none,
+          None,
+        )?],
+        (None, Some(batch)) => {
+          let batchfile = Batchfile::load(&batch)?;
+
+          let postage = batchfile
+            .postage
+            .map(Amount::from_sat)
+            .unwrap_or(postage);
+
+          batchfile.build_inscriptions(
+            chain,
+            None,
+            metadata.as_deref(),
+            postage,
+            self.compress,
+            self.skip_pointer_for_none,
+            true,
+            false,
+            &BTreeMap::new(),
+          )?
+        }
+        (None, None) => vec![Inscription::from_content(
+          chain,
+          self.content.unwrap_or_default().into_bytes(),
+          self.parent,
+          self.pointer,
+          self.metaprotocol.unwrap(),
+          metadata,
+          None,
+        )?],
+        _ => unreachable!(),
+      };
+
+      return Ok(Box::new(EnvelopePreview {
+        inscriptions: inscriptions
+          .iter()
+          .map(|inscription| EnvelopeSummary {
+            body_length: inscription.body().map(|body| body.len()),
+            content_type: inscription.content_type().map(str::to_string),
+            metadata_length: inscription.metadata.as_ref().map(Vec::len),
+            metaprotocol: inscription.metaprotocol().map(str::to_string),
+            parent: inscription.parent(),
+            pointer: inscription.pointer(),
+          })
+          .collect(),
+        reveal_script: Inscription::append_batch_reveal_script(
+          &inscriptions,
+          ScriptBuf::builder(),
+        )
+        .to_hex_string(),
+      }));
+    }
+
+    if let Some(reveal_psbt) = self.finalize_reveal_psbt {
+      let reveal_psbt = match Psbt::from_str(&reveal_psbt) {
+        Ok(psbt) => psbt,
+        Err(err) => bail!("invalid --finalize-reveal-psbt: {err}"),
+      };
+
+      let reveal_tx = Batch::finalize_reveal_psbt(reveal_psbt)?;
+
+      let reveal = if self.dry_run {
+        reveal_tx.txid()
+      } else {
+        bitcoin_rpc_client_for_wallet_command(wallet, &options)?.send_raw_transaction(&reveal_tx)?
+      };
+
+      return Ok(Box::new(Output {
+        reveal: Some(reveal),
+        reveal_hex: if dump {
+          Some(consensus::encode::serialize(&reveal_tx).raw_hex())
+        } else {
+          None
+        },
+        ..Default::default()
+      }));
+    }
+
     let index = Index::open(&options)?;
-    index.update()?;
+
+    if !self.skip_index_update {
+      index.update()?;
+    }
 
     let (mut utxos, locked_utxos, runic_utxos, client) = if self.no_wallet {
       let utxos = BTreeMap::new();
@@ -231,6 +706,8 @@ impl Inscribe {
       return Err(anyhow!(
         "--ignore-outdated-index only works in conjunction with --coin-control when inscribing"
       ));
+    } else if self.skip_index_update {
+      get_unspent_outputs_unchecked(&client)?
     } else {
       get_unspent_outputs(&client, &index)?
     };
@@ -239,6 +716,10 @@ impl Inscribe {
 
     let runic_utxos = index.get_runic_outputs(&utxos.keys().cloned().collect::<Vec<OutPoint>>())?;
 
+    if !self.allow_spend_runic {
+      Batch::check_runic_inputs(&index, &runic_utxos, &self.utxo)?;
+    }
+
     for outpoint in &self.utxo {
       utxos.insert(
         *outpoint,
@@ -258,8 +739,14 @@ impl Inscribe {
       None => None,
     };
 
+    let reveal_change = match self.reveal_change {
+      Some(reveal_change) => Some(reveal_change.require_network(chain.network())?),
+      None => None,
+    };
+
     let postage;
     let destinations;
+    let extra_outputs;
     let fee_utxos;
     let inscribe_on_specific_utxos;
     let inscriptions;
@@ -282,7 +769,7 @@ impl Inscribe {
       )?]
     } else if self.next_batch.is_some() {
       let batchfile = Batchfile::load(&self.next_batch.unwrap())?;
-      let parent_info = Inscribe::get_parent_info(batchfile.parent, &index, &utxos, &client, chain, batchfile.parent_satpoint, self.no_wallet, self.parent_destination.clone())?;
+      let parent_info = Inscribe::get_parent_info(batchfile.parent, &index, &utxos, &client, chain, batchfile.parent_satpoint, self.no_wallet, self.parent_destination.clone(), self.parent_value, self.skip_index_update)?;
       let postage = batchfile
           .postage
           .map(Amount::from_sat)
@@ -296,6 +783,7 @@ impl Inscribe {
         postage,
         self.compress,
         self.skip_pointer_for_none,
+        self.allow_unbound,
         &mut utxos,
       )?.0
     } else {
@@ -303,23 +791,48 @@ impl Inscribe {
     };
 
     match (self.file, self.batch) {
-      (Some(file), None) => {
-        parent_info = Inscribe::get_parent_info(self.parent, &index, &utxos, &client, chain, self.parent_satpoint, self.no_wallet, self.parent_destination)?;
+      (file, None) => {
+        parent_info = Inscribe::get_parent_info(self.parent, &index, &utxos, &client, chain, self.parent_satpoint, self.no_wallet, self.parent_destination, self.parent_value, self.skip_index_update)?;
 
         postage = self.postage.unwrap_or(TARGET_POSTAGE);
 
-        inscriptions = vec![Inscription::from_file(
-          chain,
-          None,
-          file,
-          self.parent,
-          None,
-          self.metaprotocol.clone(),
-          metadata.clone(),
-          self.compress,
-          self.skip_pointer_for_none,
-          None,
-        )?];
+        let pointer = match self.pointer {
+          Some(pointer) => {
+            if pointer >= postage.to_sat() {
+              return Err(anyhow!(
+                "--pointer {pointer} is outside of the {} sat inscription output",
+                postage.to_sat()
+              ));
+            }
+
+            Some(parent_info.as_ref().map(|info| info.tx_out.value).unwrap_or_default() + pointer)
+          }
+          None => None,
+        };
+
+        inscriptions = vec![match file {
+          Some(file) => Inscription::from_file(
+            chain,
+            None,
+            file,
+            self.parent,
+            pointer,
+            self.metaprotocol.clone(),
+            metadata.clone(),
+            self.compress,
+            self.skip_pointer_for_none,
+            None,
+          )?,
+          None => Inscription::from_content(
+            chain,
+            self.content.clone().unwrap_or_default().into_bytes(),
+            self.parent,
+            pointer,
+            self.metaprotocol.clone().unwrap(),
+            metadata.clone(),
+            None,
+          )?,
+        }];
 
         mode = Mode::SeparateOutputs;
 
@@ -332,11 +845,18 @@ impl Inscribe {
 
         inscribe_on_specific_utxos = false;
         fee_utxos = Vec::new();
+        extra_outputs = Vec::new();
       }
       (None, Some(batch)) => {
-        let batchfile = Batchfile::load(&batch)?;
+        let mut batchfile = Batchfile::load(&batch)?;
+
+        let sat_outputs = batchfile.assign_sats(&index, &utxos, &locked_utxos, self.no_wallet)?;
 
-        parent_info = Inscribe::get_parent_info(batchfile.parent, &index, &utxos, &client, chain, batchfile.parent_satpoint, self.no_wallet, self.parent_destination)?;
+        if !self.allow_spend_runic {
+          Batch::check_runic_inputs(&index, &runic_utxos, &sat_outputs)?;
+        }
+
+        parent_info = Inscribe::get_parent_info(batchfile.parent, &index, &utxos, &client, chain, batchfile.parent_satpoint, self.no_wallet, self.parent_destination, self.parent_value, self.skip_index_update)?;
 
         postage = batchfile
           .postage
@@ -351,9 +871,14 @@ impl Inscribe {
           postage,
           self.compress,
           self.skip_pointer_for_none,
+          self.allow_unbound,
           &mut utxos,
         )?;
 
+        if self.dedupe {
+          batchfile.warn_duplicates(&inscriptions)?;
+        }
+
         mode = batchfile.mode;
 
         if batchfile.sat.is_some() && mode != Mode::SameSat {
@@ -361,6 +886,8 @@ impl Inscribe {
         }
 
         sat = batchfile.sat;
+
+        extra_outputs = batchfile.outputs(chain)?;
       }
       _ => unreachable!(),
     }
@@ -375,24 +902,62 @@ impl Inscribe {
         Some(satpoint) => Some(satpoint),
         None => return Err(anyhow!(format!("could not find sat `{sat}`"))),
       }
+    } else if let Some(sat_range) = self.sat_range {
+      if !index.has_sat_index() {
+        return Err(anyhow!(
+          "index must be built with `--index-sats` to use `--sat-range`"
+        ));
+      }
+      match Inscribe::find_sat_in_range(&index, &utxos, sat_range)? {
+        Some(satpoint) => Some(satpoint),
+        None => return Err(anyhow!("wallet contains no sat in range `{sat_range}`")),
+      }
     } else {
       self.satpoint
     };
 
+    let commitment_output = match self.commitment {
+      Some(commitment) => {
+        let info = client.get_raw_transaction_info(&commitment.txid, None)?;
+
+        let confirmations = info.confirmations.unwrap_or_default();
+
+        if confirmations < self.min_commit_confirmations {
+          return Err(anyhow!(
+            "commitment {} has {confirmations} confirmations but --min-commit-confirmations requires {}",
+            commitment.txid,
+            self.min_commit_confirmations,
+          ));
+        }
+
+        Some(info.vout[commitment.vout as usize].clone())
+      }
+      None => None,
+    };
+
     Ok(Box::new(Batch {
+      allow_change_reuse: self.allow_change_reuse,
+      allow_spend_runic: self.allow_spend_runic,
+      broadcast_retries: self.broadcast_retries,
       commit_fee_rate: self.commit_fee_rate.unwrap_or(self.fee_rate),
+      commit_input: self.commit_input,
+      commit_locktime: self.commit_locktime.unwrap_or_default(),
       commit_only: self.commit_only,
+      commit_output_margin: self.commit_output_margin.unwrap_or(Amount::ZERO),
+      commit_tx_version: self.commit_tx_version,
       commit_vsize: self.commit_vsize,
       commitment: self.commitment,
-      commitment_output: if self.commitment.is_some() {
-        Some(client.get_raw_transaction_info(&self.commitment.unwrap().txid, None)?.vout[self.commitment.unwrap().vout as usize].clone())
-      } else {
-        None
-      },
+      commitment_output,
+      deny_change_reuse: self.deny_change_reuse,
       destinations,
       dump,
+      dump_json: self.dump_json,
       dry_run: self.dry_run,
+      estimate: self.estimate,
+      extra_outputs,
+      fee_utxo_only: self.fee_utxo_only,
       fee_utxos,
+      handoff: self.handoff,
       inscribe_on_specific_utxos,
       inscriptions,
       key: self.key,
@@ -401,29 +966,38 @@ impl Inscribe {
       no_backup,
       no_broadcast: self.no_broadcast,
       no_limit: self.no_limit,
+      no_rbf: self.no_rbf,
       no_wallet: self.no_wallet,
       parent_info,
       postage,
+      prefer_utxo: self.prefer_utxo,
+      preserve_sat_offset: self.preserve_sat_offset,
+      psbt_package: self.psbt_package,
       reinscribe: self.reinscribe,
+      reveal_change,
       reveal_fee: self.reveal_fee,
       reveal_fee_rate: self.fee_rate,
       reveal_input: self.reveal_input,
+      reveal_parent_internal_key: self.reveal_parent_internal_key,
+      reveal_parent_psbt: self.reveal_parent_psbt,
       reveal_psbt: None,
+      reveal_tx_version: self.reveal_tx_version,
       satpoint,
+      weight_warning_percent: self.weight_warning_percent,
     }
-    .inscribe(chain, &index, &client, &locked_utxos, runic_utxos, &mut utxos, self.commit_input, change)?))
+    .inscribe(chain, &index, &client, &locked_utxos, runic_utxos, &mut utxos, change)?))
   }
 
   fn parse_metadata(cbor: Option<PathBuf>, json: Option<PathBuf>) -> Result<Option<Vec<u8>>> {
     if let Some(path) = cbor {
-      let cbor = fs::read(path)?;
+      let cbor = Inscribe::read_metadata(&path)?;
       let _value: Value = ciborium::from_reader(Cursor::new(cbor.clone()))
         .context("failed to parse CBOR metadata")?;
 
       Ok(Some(cbor))
     } else if let Some(path) = json {
-      let value: serde_json::Value =
-        serde_json::from_reader(File::open(path)?).context("failed to parse JSON metadata")?;
+      let value: serde_json::Value = serde_json::from_slice(&Inscribe::read_metadata(&path)?)
+        .context("failed to parse JSON metadata")?;
       let mut cbor = Vec::new();
       ciborium::into_writer(&value, &mut cbor)?;
 
@@ -433,36 +1007,126 @@ impl Inscribe {
     }
   }
 
-  fn get_parent_info(
-    parent: Option<InscriptionId>,
-    index: &Index,
-    utxos: &BTreeMap<OutPoint, Amount>,
-    client: &Client,
-    chain: Chain,
-    satpoint: Option<SatPoint>,
-    no_wallet: bool,
-    destination: Option<Address<NetworkUnchecked>>,
-  ) -> Result<Option<ParentInfo>> {
-    if let Some(parent_id) = parent {
-      let satpoint = if let Some(satpoint) = satpoint {
-        satpoint
-      } else {
-        if let Some(satpoint) = index.get_inscription_satpoint_by_id(parent_id)? {
-          satpoint
-        } else {
-          return Err(anyhow!(format!("parent {parent_id} does not exist")));
-        }
-      };
+  fn read_metadata(path: &Path) -> Result<Vec<u8>> {
+    let source = path.to_string_lossy();
 
-      let tx_out = index
-        .get_transaction(satpoint.outpoint.txid)?
-        .expect("parent transaction not found in index")
-        .output
-        .into_iter()
-        .nth(satpoint.outpoint.vout.try_into().unwrap())
-        .expect("current transaction output");
+    if source == "-" {
+      let mut metadata = Vec::new();
+      io::Read::read_to_end(&mut io::stdin(), &mut metadata)?;
+      return Ok(metadata);
+    }
 
-      if !no_wallet && !utxos.contains_key(&satpoint.outpoint) {
+    if !(source.starts_with("http://") || source.starts_with("https://")) {
+      return Ok(fs::read(path)?);
+    }
+
+    let res = reqwest::blocking::Client::new().get(source.as_ref()).send()?;
+
+    if !res.status().is_success() {
+      bail!("failed to fetch metadata from `{source}`: {}", res.status());
+    }
+
+    let mut metadata = Vec::new();
+    io::Read::read_to_end(
+      &mut io::Read::take(res, MAX_METADATA_FETCH_SIZE + 1),
+      &mut metadata,
+    )?;
+
+    if metadata.len() as u64 > MAX_METADATA_FETCH_SIZE {
+      bail!("metadata at `{source}` exceeds {MAX_METADATA_FETCH_SIZE} bytes");
+    }
+
+    Ok(metadata)
+  }
+
+  fn find_sat_in_range(
+    index: &Index,
+    utxos: &BTreeMap<OutPoint, Amount>,
+    range: SatInterval,
+  ) -> Result<Option<SatPoint>> {
+    let mut lowest: Option<(u64, SatPoint)> = None;
+
+    for outpoint in utxos.keys() {
+      let Some(List::Unspent(sat_ranges)) = index.list(*outpoint)? else {
+        continue;
+      };
+
+      let mut offset = 0;
+      for (start, end) in sat_ranges {
+        if end > range.start.n() && start < range.end.n() {
+          let sat = start.max(range.start.n());
+
+          if lowest.map_or(true, |(lowest, _)| sat < lowest) {
+            lowest = Some((
+              sat,
+              SatPoint {
+                outpoint: *outpoint,
+                offset: offset + sat - start,
+              },
+            ));
+          }
+        }
+
+        offset += end - start;
+      }
+    }
+
+    Ok(lowest.map(|(_, satpoint)| satpoint))
+  }
+
+  fn get_parent_info(
+    parent: Option<InscriptionId>,
+    index: &Index,
+    utxos: &BTreeMap<OutPoint, Amount>,
+    client: &Client,
+    chain: Chain,
+    satpoint: Option<SatPoint>,
+    no_wallet: bool,
+    destination: Option<Address<NetworkUnchecked>>,
+    value: Option<Amount>,
+    skip_index_update: bool,
+  ) -> Result<Option<ParentInfo>> {
+    if let Some(parent_id) = parent {
+      let satpoint = if let Some(satpoint) = satpoint {
+        satpoint
+      } else {
+        if let Some(satpoint) = index.get_inscription_satpoint_by_id(parent_id)? {
+          satpoint
+        } else if skip_index_update {
+          eprintln!("warning: parent {parent_id} not found in index; fees are estimated without the parent input");
+          return Ok(None);
+        } else {
+          return Err(anyhow!(format!("parent {parent_id} does not exist")));
+        }
+      };
+
+      let txid = satpoint.outpoint.txid;
+
+      let tx_out = match (index.get_transaction(txid)?, value, &destination) {
+        (Some(transaction), _, _) => transaction
+          .output
+          .into_iter()
+          .nth(satpoint.outpoint.vout.try_into().unwrap())
+          .ok_or_else(|| anyhow!("parent satpoint {satpoint} vout out of range"))?,
+        (None, Some(value), Some(destination)) if no_wallet => TxOut {
+          value: value.to_sat(),
+          script_pubkey: destination.clone().require_network(chain.network())?.script_pubkey(),
+        },
+        (None, _, _) if no_wallet => {
+          return Err(anyhow!(
+            "parent transaction {txid} not found; in --no-wallet mode pass the value of the parent output with --parent-value and its address with --parent-destination"
+          ))
+        }
+        (None, _, _) => client
+          .get_raw_transaction(&txid, None)
+          .with_context(|| format!("parent transaction {txid} not found"))?
+          .output
+          .into_iter()
+          .nth(satpoint.outpoint.vout.try_into().unwrap())
+          .ok_or_else(|| anyhow!("parent satpoint {satpoint} vout out of range"))?,
+      };
+
+      if !no_wallet && !utxos.contains_key(&satpoint.outpoint) {
         return Err(anyhow!(format!("parent {parent_id} not in wallet")));
       }
 
@@ -485,7 +1149,70 @@ impl Inscribe {
     }
   }
 
-  fn fetch_url_into_file(
+  pub(crate) fn fetch_headers(headers: Option<&serde_json::Value>) -> Result<header::HeaderMap> {
+    let mut header_map = header::HeaderMap::new();
+    header_map.insert(USER_AGENT, header::HeaderValue::from_static("ord inscribe endpoint"));
+
+    let Some(headers) = headers else {
+      return Ok(header_map);
+    };
+
+    let Some(headers) = headers.as_object() else {
+      return Err(anyhow!("expected `fetch_headers` to be an object, not {:?}", headers));
+    };
+
+    let mut size = 0;
+
+    for (name, value) in headers {
+      let Some(value) = value.as_str() else {
+        return Err(anyhow!("expected `fetch_headers.{name}` to be a string, not {:?}", value));
+      };
+
+      let name = header::HeaderName::from_str(name)
+        .map_err(|_| anyhow!("expected `fetch_headers` to contain valid header names, not {:?}", name))?;
+
+      if [header::HOST, header::CONNECTION, header::CONTENT_LENGTH, header::TRANSFER_ENCODING].contains(&name) {
+        return Err(anyhow!("header `{name}` can't be set in `fetch_headers`"));
+      }
+
+      size += name.as_str().len() + value.len();
+
+      if size > MAX_FETCH_HEADERS_SIZE {
+        return Err(anyhow!("`fetch_headers` exceed {MAX_FETCH_HEADERS_SIZE} bytes"));
+      }
+
+      let value = header::HeaderValue::from_str(value)
+        .map_err(|_| anyhow!("expected `fetch_headers.{name}` to be a valid header value, not {:?}", value))?;
+
+      header_map.insert(name, value);
+    }
+
+    Ok(header_map)
+  }
+
+  pub(crate) fn copy_local_file(base: Option<&Path>, path: &Path, file: &PathBuf) -> Result<u64> {
+    let Some(base) = base else {
+      bail!("local files are disabled; start the server with `--inscribe-file-base` to allow them");
+    };
+
+    let base = base
+      .canonicalize()
+      .with_context(|| format!("failed to resolve `{}`", base.display()))?;
+
+    let path = path
+      .canonicalize()
+      .with_context(|| format!("failed to resolve `{}`", path.display()))?;
+
+    if !path.starts_with(&base) {
+      bail!("`{}` is outside of `{}`", path.display(), base.display());
+    }
+
+    let source = File::open(&path).map_err(|x| anyhow!("copy error: {}", x))?;
+
+    Self::write_limited(source, &path.display().to_string(), file)
+  }
+
+  pub(crate) fn fetch_url_into_file(
     client: &reqwest::blocking::Client,
     url: &str,
     file: &PathBuf,
@@ -496,14 +1223,84 @@ impl Inscribe {
       bail!(res.status());
     }
 
-    match File::create(file) {
-      Ok(mut fp) => 
-        match res.copy_to(&mut fp) {
-            Ok(n) => Ok(n),
-            Err(x) => return Err(anyhow!("write error: {}", x)),
+    let mut fp = File::create(file).map_err(|x| anyhow!("create file error: {}", x))?;
+
+    res.copy_to(&mut fp).map_err(|x| anyhow!("write error: {}", x))
+  }
+
+  // Copies at most MAX_INSCRIPTION_FETCH_SIZE bytes from a local `source` into
+  // `file`, removing `file` again if the write fails or the limit is exceeded.
+  fn write_limited(source: impl io::Read, name: &str, file: &PathBuf) -> Result<u64> {
+    let mut fp = File::create(file).map_err(|x| anyhow!("create file error: {}", x))?;
+
+    let result = match io::copy(&mut source.take(MAX_INSCRIPTION_FETCH_SIZE + 1), &mut fp) {
+      Ok(n) if n > MAX_INSCRIPTION_FETCH_SIZE => Err(anyhow!(
+        "`{name}` exceeds {MAX_INSCRIPTION_FETCH_SIZE} bytes"
+      )),
+      Ok(n) => Ok(n),
+      Err(x) => Err(anyhow!("write error: {}", x)),
+    };
+
+    if result.is_err() {
+      drop(fp);
+      let _ = fs::remove_file(file);
+    }
+
+    result
+  }
+
+  // Downloads only start once every inscription has been validated, and a
+  // failure removes everything fetched so far, including any partial file.
+  fn fetch_inscription_files(
+    client: &reqwest::blocking::Client,
+    base: Option<&Path>,
+    downloads: &[(String, Option<PathBuf>, PathBuf)],
+  ) -> Result {
+    for (i, (file, local_path, tmpfile)) in downloads.iter().enumerate() {
+      let result = match local_path {
+        Some(path) => Self::copy_local_file(base, path, tmpfile),
+        None => Self::fetch_url_into_file(client, file, tmpfile),
+      };
+
+      match result {
+        Ok(_) => {}
+        Err(err) => {
+          for (_, _, tmpfile) in &downloads[..=i] {
+            let _ = fs::remove_file(tmpfile);
+          }
+
+          bail!("error fetching inscription {i} from `{file}`: {err}");
         }
-      Err(x) => return Err(anyhow!("create file error: {}", x)),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn order_fee_utxos(
+    fees: &mut Vec<OutPoint>,
+    fee_selection: FeeSelection,
+    value: impl Fn(OutPoint) -> Result<u64>,
+  ) -> Result {
+    if fee_selection == FeeSelection::AsListed {
+      return Ok(());
     }
+
+    let mut valued = fees
+      .iter()
+      .map(|outpoint| Ok((value(*outpoint)?, *outpoint)))
+      .collect::<Result<Vec<(u64, OutPoint)>>>()?;
+
+    // stable sort, so utxos of equal value keep the order they were listed in
+    match fee_selection {
+      FeeSelection::AsListed => unreachable!(),
+      FeeSelection::LargestFirst => valued.sort_by_key(|(value, _outpoint)| std::cmp::Reverse(*value)),
+      FeeSelection::SmallestFirst => valued.sort_by_key(|(value, _outpoint)| *value),
+    }
+
+    *fees = valued.into_iter().map(|(_value, outpoint)| outpoint).collect();
+
+    Ok(())
   }
 
   pub(crate) fn get_temporary_key(
@@ -529,17 +1326,117 @@ impl Inscribe {
     }
   }
 
+  fn idempotency_record_path(index: &Index, idempotency_key: &str) -> PathBuf {
+    index
+      .data_dir()
+      .join("idempotency")
+      .join(format!("{}.json", sha256::Hash::hash(idempotency_key.as_bytes())))
+  }
+
+  fn now() -> u64 {
+    SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map(|duration| duration.as_secs())
+      .unwrap_or_default()
+  }
+
+  pub(crate) fn load_idempotent_output(
+    index: &Index,
+    idempotency_key: &str,
+    request: &sha256::Hash,
+    ttl: Duration,
+  ) -> Result<Option<Output>> {
+    let path = Self::idempotency_record_path(index, idempotency_key);
+
+    let record = match fs::read_to_string(&path) {
+      Ok(record) => record,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(err) => bail!("failed to read idempotency record `{}`: {err}", path.display()),
+    };
+
+    let record: IdempotencyRecord = serde_json::from_str(&record)
+      .with_context(|| format!("failed to parse idempotency record `{}`", path.display()))?;
+
+    if Self::now().saturating_sub(record.created) >= ttl.as_secs() {
+      fs::remove_file(&path)?;
+      return Ok(None);
+    }
+
+    if record.request != request.to_string() {
+      bail!("idempotency key {idempotency_key} was already used for a different request");
+    }
+
+    Ok(Some(record.output))
+  }
+
+  pub(crate) fn save_idempotent_output(
+    index: &Index,
+    idempotency_key: &str,
+    request: &sha256::Hash,
+    output: Output,
+  ) -> Result<Output> {
+    let path = Self::idempotency_record_path(index, idempotency_key);
+
+    fs::create_dir_all(path.parent().unwrap())?;
+
+    let record = IdempotencyRecord {
+      created: Self::now(),
+      output,
+      request: request.to_string(),
+    };
+
+    Batch::write_json(&path, &record)
+      .with_context(|| format!("failed to write idempotency record `{}`", path.display()))?;
+
+    Ok(record.output)
+  }
+
   pub(crate) fn inscribe_for_server(
     data: serde_json::Value,
-    chain: Chain,
+    server_config: &ServerConfig,
     index: &Index,
   ) -> Result<Output> {
-    let no_wallet = true;
-
     if !data.is_object() {
       return Err(anyhow!("expected object, not {:?}", data));
     }
 
+    let idempotency_key = match data.as_object().unwrap().get("idempotency_key") {
+      Some(idempotency_key) => match idempotency_key.as_str() {
+        Some(idempotency_key) => Some(idempotency_key.to_string()),
+        None => return Err(anyhow!("expected `idempotency_key` to be a string, not {:?}", idempotency_key)),
+      },
+      None => None,
+    };
+
+    let Some(idempotency_key) = idempotency_key else {
+      return Self::inscribe_for_server_uncached(data, server_config, index);
+    };
+
+    let request = sha256::Hash::hash(serde_json::to_string(&data)?.as_bytes());
+
+    let path = Self::idempotency_record_path(index, &idempotency_key);
+
+    fs::create_dir_all(path.parent().unwrap())?;
+
+    let _lock = IdempotencyLock::acquire(path.with_extension("lock"), &idempotency_key)?;
+
+    if let Some(output) = Self::load_idempotent_output(index, &idempotency_key, &request, server_config.idempotency_ttl)? {
+      return Ok(output);
+    }
+
+    let output = Self::inscribe_for_server_uncached(data, server_config, index)?;
+
+    Self::save_idempotent_output(index, &idempotency_key, &request, output)
+  }
+
+  fn inscribe_for_server_uncached(
+    data: serde_json::Value,
+    server_config: &ServerConfig,
+    index: &Index,
+  ) -> Result<Output> {
+    let no_wallet = true;
+    let chain = server_config.chain;
+
     let data = data.as_object().unwrap();
 
     if !data.contains_key("inscriptions") {
@@ -589,9 +1486,9 @@ impl Inscribe {
     let fees_utxos = fees_utxos.as_array().unwrap();
 
     let mut entries = Vec::new();
+    let mut downloads = Vec::new();
     let tmpdir = tempdir().unwrap();
-    let mut headers = header::HeaderMap::new();
-    headers.insert(USER_AGENT, header::HeaderValue::from_static("ord inscribe endpoint"));
+    let headers = Self::fetch_headers(data.get("fetch_headers"))?;
     let request_client = reqwest::blocking::Client::builder().default_headers(headers).build().unwrap();
 
     for (i, inscription) in inscriptions.iter().enumerate() {
@@ -601,6 +1498,26 @@ impl Inscribe {
 
       let inscription = inscription.as_object().unwrap();
 
+      if !inscription.contains_key("destination") {
+        return Err(anyhow!("expected `inscription` to contain `destination`"));
+      }
+      let destination = inscription.get("destination").unwrap();
+      if !destination.is_string() {
+        return Err(anyhow!("expected `inscriptions[].destination` to be a string, not {:?}", destination));
+      }
+      let destination = destination.as_str().unwrap();
+      let destination: Address<NetworkUnchecked> = match destination.parse() {
+        Ok(destination) => destination,
+        Err(_) => return Err(anyhow!("expected `inscriptions[].destination` to be a valid address, not {:?}", destination)),
+      };
+
+      // the batch file expects unchecked addresses, so only check the network when asked to
+      if server_config.strict_address_network {
+        if let Err(err) = destination.clone().require_network(chain.network()) {
+          return Err(anyhow!("expected `inscriptions[{i}].destination` to be valid for {chain}: {err}"));
+        }
+      }
+
       if !inscription.contains_key("file") {
         return Err(anyhow!("expected `inscription` to contain `file`"));
       }
@@ -609,20 +1526,28 @@ impl Inscribe {
         return Err(anyhow!("expected `inscriptions[].file` to be a string, not {:?}", file));
       }
       let file = file.as_str().unwrap();
-      let url = Url::parse(file)?;
-      let path = PathBuf::from(url.path());
+      let local_path = if file.starts_with('/') {
+        Some(PathBuf::from(file))
+      } else {
+        let url = Url::parse(file)?;
+        if url.scheme() == "file" {
+          match url.to_file_path() {
+            Ok(path) => Some(path),
+            Err(()) => return Err(anyhow!("expected file URL {:?} to contain a local path", file)),
+          }
+        } else {
+          None
+        }
+      };
+      let path = match &local_path {
+        Some(path) => path.clone(),
+        None => PathBuf::from(Url::parse(file)?.path()),
+      };
       let ext = match path.extension() {
         Some(ext) => ext,
         None => return Err(anyhow!("expected URL {:?} path {:?} to have a file extension", file, path)),
       };
       let tmpfile = tmpdir.path().join(format!("{i}.{}", ext.to_str().unwrap()));
-      match Self::fetch_url_into_file(&request_client, file, &tmpfile) {
-        Ok(body) => {
-          eprintln!("body is {} bytes", body);
-          let _ = fs::copy(&tmpfile, "/tmp/file");
-        }
-        Err(e) => return Err(anyhow!("error fetching {} : {}", file, e)),
-      };
 
       if !inscription.contains_key("utxo") {
         return Err(anyhow!("expected `inscription` to contain `utxo`"));
@@ -643,30 +1568,12 @@ impl Inscribe {
         None
       };
 
-      if !inscription.contains_key("destination") {
-        return Err(anyhow!("expected `inscription` to contain `destination`"));
-      }
-      let destination = inscription.get("destination").unwrap();
-      if !destination.is_string() {
-        return Err(anyhow!("expected `inscriptions[].destination` to be a string, not {:?}", destination));
-      }
-      let destination = destination.as_str().unwrap();
-      let destination: Address<NetworkUnchecked> = match destination.parse() {
-        Ok(destination) => destination,
-        Err(_) => return Err(anyhow!("expected `inscriptions[].destination` to be a valid address, not {:?}", destination)),
-      };
-
-      /* we don't need to check addresses for the correct network type here, because the batch file expects unchecked addresses
-         let destination: Address = match destination.clone().require_network(chain.network()) {
-           Ok(destination) => destination,
-           Err(_) => return Err(anyhow!("expected `inscriptions[].destination` to be valid for the current chain, not {:?}", destination)),
-         };
-       */
-
       entries.push(BatchEntry {
+        compress: None,
+        content: None,
         delegate: None,
         destination: Some(destination),
-        file: tmpfile.into(),
+        file: Some(tmpfile.clone()),
         metadata: None,
         metadata_json: metadata,
         metaprotocol: None,
@@ -674,8 +1581,16 @@ impl Inscribe {
         pointer: None,
         utxo: Some(utxo),
       });
+
+      downloads.push((file.to_string(), local_path, tmpfile));
     }
 
+    Self::fetch_inscription_files(
+      &request_client,
+      server_config.inscribe_file_base.as_deref(),
+      &downloads,
+    )?;
+
     let mut fees = Vec::new();
 
     for fees_utxo in fees_utxos {
@@ -692,6 +1607,28 @@ impl Inscribe {
       fees.push(fees_utxo);
     }
 
+    let fee_selection = if data.contains_key("fee_selection") {
+      let fee_selection = data.get("fee_selection").unwrap();
+      if !fee_selection.is_string() {
+        return Err(anyhow!("expected `fee_selection` to be a string, not {:?}", fee_selection));
+      }
+      fee_selection.as_str().unwrap().parse::<FeeSelection>()?
+    } else {
+      FeeSelection::AsListed
+    };
+
+    Self::order_fee_utxos(&mut fees, fee_selection, |outpoint| {
+      Ok(
+        index
+          .client()
+          .get_raw_transaction(&outpoint.txid, None)?
+          .output
+          .get(outpoint.vout as usize)
+          .ok_or_else(|| anyhow!("fee utxo {outpoint} vout out of range"))?
+          .value,
+      )
+    })?;
+
     let batchfile = Batchfile {
       fees: Some(fees),
       inscriptions: entries,
@@ -700,6 +1637,26 @@ impl Inscribe {
       ..Default::default()
     };
 
+    let dedupe = if data.contains_key("dedupe") {
+      let dedupe = data.get("dedupe").unwrap();
+      if !dedupe.is_boolean() {
+        return Err(anyhow!("expected `dedupe` to be a boolean, not {:?}", dedupe));
+      }
+      dedupe.as_bool().unwrap()
+    } else {
+      false
+    };
+
+    let fee_utxo_only = if data.contains_key("fee_utxo_only") {
+      let fee_utxo_only = data.get("fee_utxo_only").unwrap();
+      if !fee_utxo_only.is_boolean() {
+        return Err(anyhow!("expected `fee_utxo_only` to be a boolean, not {:?}", fee_utxo_only));
+      }
+      fee_utxo_only.as_bool().unwrap()
+    } else {
+      false
+    };
+
     let mut utxos = BTreeMap::new();
     let locked_utxos = BTreeSet::new();
     let runic_utxos = BTreeSet::new();
@@ -718,7 +1675,7 @@ impl Inscribe {
 
     let compress = false;
 
-        parent_info = Inscribe::get_parent_info(batchfile.parent, &index, &utxos, &client, chain, batchfile.parent_satpoint, no_wallet, None)?;
+        parent_info = Inscribe::get_parent_info(batchfile.parent, index, &utxos, client, chain, batchfile.parent_satpoint, no_wallet, None, None, false)?;
 
         postage = batchfile
           .postage
@@ -733,8 +1690,14 @@ impl Inscribe {
           Amount::from_sat(0),
           compress,
           false,
+          false,
           &mut utxos,
         )?;
+
+        if dedupe {
+          batchfile.warn_duplicates(&inscriptions)?;
+        }
+
         next_inscriptions = Vec::new();
 
         mode = batchfile.mode;
@@ -764,15 +1727,28 @@ impl Inscribe {
     };
 
     Batch {
+      allow_change_reuse: false,
+      allow_spend_runic: false,
+      broadcast_retries: 0,
       commit_fee_rate: FeeRate::try_from(0.0).unwrap(),
+      commit_input: Vec::new(),
+      commit_locktime: 0,
       commit_only: false,
+      commit_output_margin: Amount::ZERO,
+      commit_tx_version: 2,
       commit_vsize,
       commitment: None,
       commitment_output: None,
+      deny_change_reuse: false,
       destinations,
       dump: true,
+      dump_json: None,
       dry_run: false,
+      estimate: false,
+      extra_outputs: Vec::new(),
+      fee_utxo_only,
       fee_utxos,
+      handoff: None,
       inscribe_on_specific_utxos,
       inscriptions,
       key,
@@ -781,37 +1757,88 @@ impl Inscribe {
       no_backup: true,
       no_broadcast: true,
       no_limit: false,
+      no_rbf: false,
       no_wallet,
       parent_info,
       postage,
+      prefer_utxo: Vec::new(),
+      preserve_sat_offset: false,
+      psbt_package: false,
       reinscribe: false,
+      reveal_change: None,
       reveal_fee: None,
       reveal_fee_rate: FeeRate::try_from(0.0).unwrap(),
       reveal_input: Vec::new(),
+      reveal_parent_internal_key: None,
+      reveal_parent_psbt: false,
       reveal_psbt,
+      reveal_tx_version: 2,
       satpoint,
+      weight_warning_percent: 90,
     }
-    .inscribe(chain, &index, &client, &locked_utxos, runic_utxos, &mut utxos, Vec::new(), change)
+    .inscribe(chain, index, client, &locked_utxos, runic_utxos, &mut utxos, change)
   }
 }
 
 #[cfg(test)]
 mod tests {
   use {
-    self::batch::BatchEntry,
+    self::batch::{BatchEntry, BatchTransactions},
     super::*,
+    crate::index::testing::Context,
     serde_yaml::{Mapping, Value},
   };
 
+  // Serves `body` with a 200 to every request, and records the requests.
+  fn mock_http_server(body: &'static [u8]) -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = requests.clone();
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+          let n = io::Read::read(&mut stream, &mut buffer).unwrap();
+          if n == 0 {
+            break;
+          }
+          request.extend_from_slice(&buffer[..n]);
+        }
+        recorded
+          .lock()
+          .unwrap()
+          .push(String::from_utf8(request).unwrap());
+        stream
+          .write_all(
+            format!(
+              "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+              body.len()
+            )
+            .as_bytes(),
+          )
+          .unwrap();
+        stream.write_all(body).unwrap();
+      }
+    });
+
+    (port, requests)
+  }
+
   #[test]
   fn reveal_transaction_pays_fee() {
+    let context = Context::builder().build();
+
     let utxos = vec![(outpoint(1), Amount::from_sat(20000))];
     let inscription = inscription("text/plain", "ord");
     let commit_address = change(0);
     let reveal_address = recipient();
     let change = [commit_address, change(1)];
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint: Some(satpoint(1, 0)),
       parent_info: None,
       inscriptions: vec![inscription],
@@ -826,17 +1853,23 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       BTreeMap::new(),
+      &context.index,
       Chain::Mainnet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      change,
+      Some(change),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_sign_loss)]
-    let fee = Amount::from_sat((1.0 * (reveal_tx.vsize() as f64)).ceil() as u64);
+    let fee = Amount::from_sat((1.0_f64 * (reveal_tx.vsize() as f64)).ceil() as u64);
 
     assert_eq!(
       reveal_tx.output[0].value,
@@ -846,41 +1879,93 @@ mod tests {
 
   #[test]
   fn inscribe_transactions_opt_in_to_rbf() {
-    let utxos = vec![(outpoint(1), Amount::from_sat(20000))];
-    let inscription = inscription("text/plain", "ord");
-    let commit_address = change(0);
-    let reveal_address = recipient();
-    let change = [commit_address, change(1)];
+    let context = Context::builder().build();
+
+    for no_rbf in [false, true] {
+      let utxos = vec![(outpoint(1), Amount::from_sat(20000))];
+      let inscription = inscription("text/plain", "ord");
+      let commit_address = change(0);
+      let reveal_address = recipient();
+      let change = [commit_address, change(1)];
+
+      let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
+        satpoint: Some(satpoint(1, 0)),
+        parent_info: None,
+        inscriptions: vec![inscription],
+        destinations: vec![reveal_address],
+        commit_fee_rate: FeeRate::try_from(1.0).unwrap(),
+        reveal_fee_rate: FeeRate::try_from(1.0).unwrap(),
+        no_limit: false,
+        no_rbf,
+        reinscribe: false,
+        postage: TARGET_POSTAGE,
+        mode: Mode::SharedOutput,
+        ..Default::default()
+      }
+      .create_batch_inscription_transactions(
+        BTreeMap::new(),
+        &context.index,
+        Chain::Mainnet,
+        BTreeSet::new(),
+        BTreeSet::new(),
+        utxos.into_iter().collect(),
+        Some(change),
+        None,
+        context.index.client(),
+      )
+      .unwrap();
 
-    let (commit_tx, reveal_tx, _, _) = Batch {
-      satpoint: Some(satpoint(1, 0)),
-      parent_info: None,
-      inscriptions: vec![inscription],
-      destinations: vec![reveal_address],
-      commit_fee_rate: FeeRate::try_from(1.0).unwrap(),
-      reveal_fee_rate: FeeRate::try_from(1.0).unwrap(),
-      no_limit: false,
-      reinscribe: false,
-      postage: TARGET_POSTAGE,
-      mode: Mode::SharedOutput,
-      ..Default::default()
+      let commit_tx = commit_tx.unwrap();
+      let reveal_tx = reveal_tx.unwrap();
+
+      assert!(commit_tx.is_explicitly_rbf());
+      assert_eq!(reveal_tx.is_explicitly_rbf(), !no_rbf);
     }
-    .create_batch_inscription_transactions(
-      BTreeMap::new(),
-      Chain::Mainnet,
-      BTreeSet::new(),
-      BTreeSet::new(),
-      utxos.into_iter().collect(),
-      change,
-    )
-    .unwrap();
+  }
+
+  #[test]
+  fn no_rbf_reveal_transaction_does_not_signal_rbf() {
+    let secp256k1 = Secp256k1::new();
+    let key_pair = UntweakedKeyPair::new(&secp256k1, &mut rand::thread_rng());
+    let (public_key, _parity) = XOnlyPublicKey::from_keypair(&key_pair);
+
+    let reveal_script = inscription("text/plain", "ord").append_reveal_script(
+      ScriptBuf::builder()
+        .push_slice(public_key.serialize())
+        .push_opcode(opcodes::all::OP_CHECKSIG),
+    );
+
+    let control_block = TaprootBuilder::new()
+      .add_leaf(0, reveal_script.clone())
+      .unwrap()
+      .finalize(&secp256k1, public_key)
+      .unwrap()
+      .control_block(&(reveal_script.clone(), LeafVersion::TapScript))
+      .unwrap();
+
+    for no_rbf in [false, true] {
+      let (reveal_tx, _fee, _vsize) = Batch::build_reveal_transaction(
+        &control_block,
+        FeeRate::try_from(1.0).unwrap(),
+        vec![outpoint(1)],
+        0,
+        vec![TxOut {
+          script_pubkey: recipient().script_pubkey(),
+          value: TARGET_POSTAGE.to_sat(),
+        }],
+        &reveal_script,
+        no_rbf,
+        2,
+      );
 
-    assert!(commit_tx.is_explicitly_rbf());
-    assert!(reveal_tx.is_explicitly_rbf());
+      assert_eq!(reveal_tx.is_explicitly_rbf(), !no_rbf);
+    }
   }
 
   #[test]
   fn inscribe_with_no_satpoint_and_no_cardinal_utxos() {
+    let context = Context::builder().build();
+
     let utxos = vec![(outpoint(1), Amount::from_sat(1000))];
     let mut inscriptions = BTreeMap::new();
     inscriptions.insert(
@@ -911,11 +1996,14 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       inscriptions,
+      &context.index,
       Chain::Mainnet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .unwrap_err()
     .to_string();
@@ -929,6 +2017,8 @@ mod tests {
 
   #[test]
   fn inscribe_with_no_satpoint_and_enough_cardinal_utxos() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(20_000)),
       (outpoint(2), Amount::from_sat(20_000)),
@@ -962,17 +2052,22 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       inscriptions,
+      &context.index,
       Chain::Mainnet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .is_ok())
   }
 
   #[test]
   fn inscribe_with_custom_fee_rate() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(20_000)),
@@ -992,7 +2087,7 @@ mod tests {
     let reveal_address = recipient();
     let fee_rate = 3.3;
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint,
       parent_info: None,
       inscriptions: vec![inscription],
@@ -1007,14 +2102,20 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     let sig_vbytes = 17;
     let fee = FeeRate::try_from(fee_rate)
       .unwrap()
@@ -1043,6 +2144,8 @@ mod tests {
 
   #[test]
   fn inscribe_with_parent() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(20_000)),
@@ -1075,7 +2178,7 @@ mod tests {
     let reveal_address = recipient();
     let fee_rate = 4.0;
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint: None,
       parent_info: Some(parent_info.clone()),
       inscriptions: vec![child_inscription],
@@ -1090,14 +2193,20 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     let sig_vbytes = 17;
     let fee = FeeRate::try_from(fee_rate)
       .unwrap()
@@ -1137,6 +2246,8 @@ mod tests {
 
   #[test]
   fn inscribe_with_commit_fee_rate() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(20_000)),
@@ -1157,7 +2268,7 @@ mod tests {
     let commit_fee_rate = 3.3;
     let fee_rate = 1.0;
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint,
       parent_info: None,
       inscriptions: vec![inscription],
@@ -1172,14 +2283,20 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     let sig_vbytes = 17;
     let fee = FeeRate::try_from(commit_fee_rate)
       .unwrap()
@@ -1208,6 +2325,8 @@ mod tests {
 
   #[test]
   fn inscribe_over_max_standard_tx_weight() {
+    let context = Context::builder().build();
+
     let utxos = vec![(outpoint(1), Amount::from_sat(50 * COIN_VALUE))];
 
     let inscription = inscription("text/plain", [0; MAX_STANDARD_TX_WEIGHT as usize]);
@@ -1230,11 +2349,14 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       BTreeMap::new(),
+      &context.index,
       Chain::Mainnet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .unwrap_err()
     .to_string();
@@ -1248,6 +2370,8 @@ mod tests {
 
   #[test]
   fn inscribe_with_no_max_standard_tx_weight() {
+    let context = Context::builder().build();
+
     let utxos = vec![(outpoint(1), Amount::from_sat(50 * COIN_VALUE))];
 
     let inscription = inscription("text/plain", [0; MAX_STANDARD_TX_WEIGHT as usize]);
@@ -1255,7 +2379,7 @@ mod tests {
     let commit_address = change(0);
     let reveal_address = recipient();
 
-    let (_commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { reveal_tx, .. } = Batch {
       satpoint,
       parent_info: None,
       inscriptions: vec![inscription],
@@ -1270,14 +2394,19 @@ mod tests {
     }
     .create_batch_inscription_transactions(
       BTreeMap::new(),
+      &context.index,
       Chain::Mainnet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(1)],
+      Some([commit_address, change(1)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let reveal_tx = reveal_tx.unwrap();
+
     assert!(reveal_tx.size() >= MAX_STANDARD_TX_WEIGHT as usize);
   }
 
@@ -1346,12 +2475,12 @@ inscriptions:
       Batchfile {
         inscriptions: vec![
           BatchEntry {
-            file: inscription_path,
+            file: Some(inscription_path),
             metadata: Some(Value::Mapping(metadata)),
             ..Default::default()
           },
           BatchEntry {
-            file: brc20_path,
+            file: Some(brc20_path),
             metaprotocol: Some("brc-20".to_string()),
             ..Default::default()
           }
@@ -1380,6 +2509,8 @@ inscriptions:
 
   #[test]
   fn batch_inscribe_with_parent() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(50_000)),
@@ -1428,7 +2559,7 @@ inscriptions:
 
     let fee_rate = 4.0.try_into().unwrap();
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint: None,
       parent_info: Some(parent_info.clone()),
       inscriptions,
@@ -1443,14 +2574,20 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     let sig_vbytes = 17;
     let fee = fee_rate.fee(commit_tx.vsize() + sig_vbytes).to_sat();
 
@@ -1484,6 +2621,8 @@ inscriptions:
 
   #[test]
   fn batch_inscribe_with_parent_not_enough_cardinals_utxos_fails() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(20_000)),
@@ -1543,11 +2682,14 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap_err()
     .to_string();
@@ -1562,6 +2704,8 @@ inscriptions:
     expected = "invariant: destination addresses and number of inscriptions doesn't match"
   )]
   fn batch_inscribe_with_inconsistent_reveal_addresses_panics() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(80_000)),
@@ -1621,16 +2765,21 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     );
   }
 
   #[test]
   fn batch_inscribe_over_max_standard_tx_weight() {
+    let context = Context::builder().build();
+
     let utxos = vec![(outpoint(1), Amount::from_sat(50 * COIN_VALUE))];
 
     let wallet_inscriptions = BTreeMap::new();
@@ -1659,11 +2808,14 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap_err()
     .to_string();
@@ -1677,6 +2829,8 @@ inscriptions:
 
   #[test]
   fn batch_inscribe_into_separate_outputs() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(80_000)),
@@ -1697,7 +2851,7 @@ inscriptions:
 
     let fee_rate = 4.0.try_into().unwrap();
 
-    let (_commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { reveal_tx, .. } = Batch {
       satpoint: None,
       parent_info: None,
       inscriptions,
@@ -1712,14 +2866,19 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let reveal_tx = reveal_tx.unwrap();
+
     assert_eq!(reveal_tx.output.len(), 3);
     assert!(reveal_tx
       .output
@@ -1729,6 +2888,8 @@ inscriptions:
 
   #[test]
   fn batch_inscribe_into_separate_outputs_with_parent() {
+    let context = Context::builder().build();
+
     let utxos = vec![
       (outpoint(1), Amount::from_sat(10_000)),
       (outpoint(2), Amount::from_sat(50_000)),
@@ -1777,7 +2938,7 @@ inscriptions:
 
     let fee_rate = 4.0.try_into().unwrap();
 
-    let (commit_tx, reveal_tx, _private_key, _) = Batch {
+    let BatchTransactions { commit_tx, reveal_tx, .. } = Batch {
       satpoint: None,
       parent_info: Some(parent_info.clone()),
       inscriptions,
@@ -1792,24 +2953,30 @@ inscriptions:
     }
     .create_batch_inscription_transactions(
       wallet_inscriptions,
+      &context.index,
       Chain::Signet,
       BTreeSet::new(),
       BTreeSet::new(),
       utxos.into_iter().collect(),
-      [commit_address, change(2)],
+      Some([commit_address, change(2)]),
+      None,
+      context.index.client(),
     )
     .unwrap();
 
+    let commit_tx = commit_tx.unwrap();
+    let reveal_tx = reveal_tx.unwrap();
+
     assert_eq!(
       parent,
-      ParsedEnvelope::from_transaction(&reveal_tx)[0]
+      ParsedEnvelope::from_transaction(&reveal_tx, false)[0]
         .payload
         .parent()
         .unwrap()
     );
     assert_eq!(
       parent,
-      ParsedEnvelope::from_transaction(&reveal_tx)[1]
+      ParsedEnvelope::from_transaction(&reveal_tx, false)[1]
         .payload
         .parent()
         .unwrap()
@@ -1855,14 +3022,13 @@ inscriptions:
         "--destination",
         Some("tb1qsgx55dp6gn53tsmyjjv4c2ye403hgxynxs0dnm"),
       ),
-      ("--cbor-metadata", Some("foo")),
-      ("--json-metadata", Some("foo")),
       (
         "--satpoint",
         Some("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b:0:0"),
       ),
       ("--reinscribe", None),
       ("--metaprotocol", Some("foo")),
+      ("--pointer", Some("100")),
       (
         "--parent",
         Some("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33bi0"),
@@ -1896,10 +3062,40 @@ inscriptions:
       Arguments::try_parse_from(["ord", "wallet", "inscribe", "--fee-rate", "1",])
         .unwrap_err()
         .to_string()
-        .contains("error: the following required arguments were not provided:\n  <--file <FILE>|--batch <BATCH>>")
+        .contains("error: the following required arguments were not provided:\n  <--file <FILE>|--batch <BATCH>|--content <CONTENT>|--finalize-reveal-psbt <FINALIZE_REVEAL_PSBT>>")
     );
   }
 
+  #[test]
+  fn weight_warning_percent_must_be_between_1_and_99() {
+    let parse = |percent: &str| {
+      Arguments::try_parse_from([
+        "ord",
+        "wallet",
+        "inscribe",
+        "--fee-rate",
+        "1",
+        "--file",
+        "foo.txt",
+        "--weight-warning-percent",
+        percent,
+      ])
+    };
+
+    for percent in ["1", "99"] {
+      parse(percent).unwrap();
+    }
+
+    for percent in ["0", "100"] {
+      assert_regex_match!(
+        parse(percent).unwrap_err().to_string(),
+        format!(
+          ".*invalid value '{percent}' for '--weight-warning-percent <WEIGHT_WARNING_PERCENT>': {percent} is not in 1..=99.*"
+        ),
+      );
+    }
+  }
+
   #[test]
   fn satpoint_and_sat_flags_conflict() {
     assert_regex_match!(
@@ -1920,4 +3116,1606 @@ inscriptions:
       ".*--sat.*cannot be used with.*--satpoint.*"
     );
   }
+
+  #[test]
+  fn pointer_is_included_in_reveal_envelope() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("foo.txt");
+    fs::write(&path, "foo").unwrap();
+
+    let inscription = Inscription::from_file(
+      Chain::Mainnet,
+      None,
+      &path,
+      None,
+      Some(1234),
+      None,
+      None,
+      false,
+      false,
+      None,
+    )
+    .unwrap();
+
+    let script = inscription.append_reveal_script(ScriptBuf::builder());
+
+    let envelopes = ParsedEnvelope::from_transaction(
+      &Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+          previous_output: OutPoint::null(),
+          script_sig: ScriptBuf::new(),
+          sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+          witness: Witness::from_slice(&[script.into_bytes(), Vec::new()]),
+        }],
+        output: Vec::new(),
+      },
+      false,
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(envelopes[0].payload.pointer(), Some(1234));
+  }
+
+  #[test]
+  fn batch_entry_metadata_is_merged_over_global_metadata() {
+    let mut global = Vec::new();
+    ciborium::into_writer(&serde_json::json!({ "title": "foo" }), &mut global).unwrap();
+
+    let entry = BatchEntry {
+      file: Some("inscription.txt".into()),
+      metadata_json: Some(serde_json::json!({ "description": "bar" })),
+      ..Default::default()
+    };
+
+    let mut expected = Vec::new();
+    ciborium::into_writer(
+      &serde_json::json!({ "title": "foo", "description": "bar" }),
+      &mut expected,
+    )
+    .unwrap();
+
+    assert_eq!(entry.metadata(Some(&global)).unwrap(), Some(expected));
+
+    let entry = BatchEntry {
+      file: Some("inscription.txt".into()),
+      metadata_json: Some(serde_json::json!({ "title": "baz" })),
+      ..Default::default()
+    };
+
+    let mut expected = Vec::new();
+    ciborium::into_writer(&serde_json::json!({ "title": "baz" }), &mut expected).unwrap();
+
+    assert_eq!(entry.metadata(Some(&global)).unwrap(), Some(expected));
+  }
+
+  #[test]
+  fn batch_entry_metadata_that_is_not_a_map_cannot_be_merged() {
+    let mut global = Vec::new();
+    ciborium::into_writer(&serde_json::json!({ "title": "foo" }), &mut global).unwrap();
+
+    let entry = BatchEntry {
+      file: Some("inscription.txt".into()),
+      metadata_json: Some(serde_json::json!(["bar"])),
+      ..Default::default()
+    };
+
+    assert_regex_match!(
+      entry.metadata(Some(&global)).unwrap_err().to_string(),
+      "metadata can only be merged if both global and per-inscription metadata are maps"
+    );
+  }
+
+  #[test]
+  fn missing_reveal_input_is_an_error() {
+    let context = Context::builder().build();
+
+    assert_regex_match!(
+      Batch::get_reveal_input_output(&context.index, outpoint(1))
+        .unwrap_err()
+        .to_string(),
+      "reveal input .*:1 not found in index"
+    );
+  }
+
+  #[test]
+  fn reveal_input_with_out_of_range_vout_is_an_error() {
+    let context = Context::builder().build();
+
+    let txid = context.rpc_server.mine_blocks(1)[0].txdata[0].txid();
+
+    assert_regex_match!(
+      Batch::get_reveal_input_output(&context.index, OutPoint { txid, vout: 100 })
+        .unwrap_err()
+        .to_string(),
+      "reveal input .*:100 vout out of range"
+    );
+  }
+
+  #[test]
+  fn batch_entry_compress_overrides_compress_flag() {
+    let context = Context::builder().build();
+
+    let tempdir = TempDir::new().unwrap();
+    let compressed = tempdir.path().join("compressed.txt");
+    let uncompressed = tempdir.path().join("uncompressed.txt");
+    fs::write(&compressed, "a".repeat(1000)).unwrap();
+    fs::write(&uncompressed, "a".repeat(1000)).unwrap();
+
+    let (inscriptions, _destinations, _inscribe_on_specific_utxos, _fees) = Batchfile {
+      inscriptions: vec![
+        BatchEntry {
+          file: Some(compressed),
+          ..Default::default()
+        },
+        BatchEntry {
+          compress: Some(false),
+          file: Some(uncompressed),
+          ..Default::default()
+        },
+      ],
+      ..Default::default()
+    }
+    .inscriptions(
+      context.index.client(),
+      Chain::Regtest,
+      None,
+      None,
+      TARGET_POSTAGE,
+      true,
+      false,
+      false,
+      &mut BTreeMap::new(),
+    )
+    .unwrap();
+
+    assert_eq!(inscriptions[0].content_encoding, Some("br".as_bytes().to_vec()));
+    assert_eq!(inscriptions[1].content_encoding, None);
+  }
+
+  #[test]
+  fn batch_entry_without_file_requires_metaprotocol() {
+    let context = Context::builder().build();
+
+    let inscriptions = |entry: BatchEntry| {
+      Batchfile {
+        inscriptions: vec![entry],
+        ..Default::default()
+      }
+      .inscriptions(
+        context.index.client(),
+        Chain::Regtest,
+        None,
+        None,
+        TARGET_POSTAGE,
+        false,
+        false,
+        false,
+        &mut BTreeMap::new(),
+      )
+      .map(|(inscriptions, _, _, _)| inscriptions)
+    };
+
+    let inscription = inscriptions(BatchEntry {
+      content: Some(r#"{"p":"brc-20","op":"mint"}"#.into()),
+      metaprotocol: Some("brc-20".into()),
+      ..Default::default()
+    })
+    .unwrap()
+    .remove(0);
+
+    assert_eq!(inscription.content_type(), None);
+    assert_eq!(inscription.metaprotocol(), Some("brc-20"));
+    assert_eq!(inscription.body(), Some(r#"{"p":"brc-20","op":"mint"}"#.as_bytes()));
+
+    assert_eq!(
+      inscriptions(BatchEntry {
+        content: Some("foo".into()),
+        ..Default::default()
+      })
+      .unwrap_err()
+      .to_string(),
+      "inscription 0 has no `file`; inscriptions without a file must set `metaprotocol`",
+    );
+  }
+
+  #[test]
+  fn fee_utxo_only_does_not_spend_other_wallet_utxos() {
+    let context = Context::builder().build();
+
+    let utxos = [
+      (outpoint(1), Amount::from_sat(10_000)),
+      (outpoint(2), Amount::from_sat(100)),
+      (outpoint(3), Amount::from_sat(100_000_000)),
+    ];
+
+    let batch = |fee_utxo_only| Batch {
+      commit_input: vec![outpoint(2)],
+      satpoint: Some(satpoint(1, 0)),
+      inscriptions: vec![inscription("text/plain", "ord")],
+      destinations: vec![recipient()],
+      commit_fee_rate: FeeRate::try_from(1.0).unwrap(),
+      reveal_fee_rate: FeeRate::try_from(1.0).unwrap(),
+      postage: Amount::from_sat(10_000),
+      mode: Mode::SeparateOutputs,
+      fee_utxo_only,
+      ..Default::default()
+    };
+
+    assert_regex_match!(
+      batch(true)
+        .create_batch_inscription_transactions(
+          BTreeMap::new(),
+          &context.index,
+          Chain::Mainnet,
+          BTreeSet::new(),
+          BTreeSet::new(),
+          utxos.into_iter().collect(),
+          Some([change(0), change(1)]),
+          None,
+          context.index.client(),
+        )
+        .unwrap_err()
+        .to_string(),
+      r"fee utxos insufficient: need \d+ more sats",
+    );
+
+    let BatchTransactions { commit_tx, .. } = batch(false)
+      .create_batch_inscription_transactions(
+        BTreeMap::new(),
+        &context.index,
+        Chain::Mainnet,
+        BTreeSet::new(),
+        BTreeSet::new(),
+        utxos.into_iter().collect(),
+        Some([change(0), change(1)]),
+        None,
+        context.index.client(),
+      )
+      .unwrap();
+
+    assert!(commit_tx
+      .unwrap()
+      .input
+      .iter()
+      .any(|input| input.previous_output == outpoint(3)));
+  }
+
+  #[test]
+  fn preferred_utxo_is_chosen_as_commit_satpoint() {
+    let context = Context::builder().build();
+
+    let utxos = [
+      (outpoint(1), Amount::from_sat(100_000)),
+      (outpoint(2), Amount::from_sat(100_000)),
+      (outpoint(3), Amount::from_sat(100_000)),
+    ];
+
+    let BatchTransactions { commit_tx, .. } = Batch {
+      inscriptions: vec![inscription("text/plain", "ord")],
+      destinations: vec![recipient()],
+      mode: Mode::SeparateOutputs,
+      prefer_utxo: vec![outpoint(4), outpoint(3), outpoint(2)],
+      ..Default::default()
+    }
+    .create_batch_inscription_transactions(
+      BTreeMap::new(),
+      &context.index,
+      Chain::Mainnet,
+      [outpoint(3)].into_iter().collect(),
+      BTreeSet::new(),
+      utxos.into_iter().collect(),
+      Some([change(0), change(1)]),
+      None,
+      context.index.client(),
+    )
+    .unwrap();
+
+    assert_eq!(commit_tx.unwrap().input[0].previous_output, outpoint(2));
+  }
+
+  #[test]
+  fn fee_utxos_sufficient_boundary() {
+    let commit_utxos = [(outpoint(1), Amount::from_sat(1_000))]
+      .into_iter()
+      .collect::<BTreeMap<OutPoint, Amount>>();
+
+    assert!(Batch::check_fee_utxos_sufficient(
+      &commit_utxos,
+      Amount::from_sat(900),
+      Amount::from_sat(100),
+    )
+    .is_ok());
+
+    assert_eq!(
+      Batch::check_fee_utxos_sufficient(&commit_utxos, Amount::from_sat(900), Amount::from_sat(150))
+        .unwrap_err()
+        .to_string(),
+      "fee utxos insufficient: need 50 more sats",
+    );
+  }
+
+  #[test]
+  fn commit_output_margin_boundary() {
+    let commit_output = |value| TxOut {
+      script_pubkey: recipient().script_pubkey(),
+      value,
+    };
+
+    let dust = recipient().script_pubkey().dust_value().to_sat();
+    let postage = Amount::from_sat(10_000);
+    let reveal_fee = Amount::from_sat(1_000);
+    let margin = Amount::from_sat(500);
+    let required = dust + 10_000 + 1_000 + 500;
+
+    Batch::check_commit_output_margin(&commit_output(required), postage, reveal_fee, margin)
+      .unwrap();
+
+    assert_regex_match!(
+      Batch::check_commit_output_margin(&commit_output(required - 1), postage, reveal_fee, margin)
+        .unwrap_err()
+        .to_string(),
+      format!("commit output of {} sats does not cover .*; {required} sats required", required - 1)
+    );
+  }
+
+  #[test]
+  fn zero_commit_output_margin_is_not_checked() {
+    Batch::check_commit_output_margin(
+      &TxOut {
+        script_pubkey: recipient().script_pubkey(),
+        value: 0,
+      },
+      Amount::from_sat(10_000),
+      Amount::from_sat(1_000),
+      Amount::ZERO,
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn forced_runic_input_is_an_error() {
+    let context = Context::builder().build();
+
+    let mut runic_utxos = BTreeSet::new();
+    runic_utxos.insert(outpoint(1));
+
+    Batch::check_runic_inputs(&context.index, &runic_utxos, &[outpoint(2)]).unwrap();
+
+    assert_regex_match!(
+      Batch::check_runic_inputs(&context.index, &runic_utxos, &[outpoint(2), outpoint(1)])
+        .unwrap_err()
+        .to_string(),
+      "input .*:1 contains runes; use --allow-spend-runic to spend it anyway"
+    );
+  }
+
+  #[test]
+  fn duplicate_change_addresses_are_retried() {
+    let mut addresses = vec![change(0), change(0), change(1)].into_iter();
+
+    assert_eq!(
+      Batch::change_addresses(vec![None, None], false, false, &mut Vec::new(), || {
+        Ok(addresses.next().unwrap())
+      })
+      .unwrap(),
+      vec![change(0), change(1)],
+    );
+  }
+
+  #[test]
+  fn wallet_that_only_returns_duplicate_change_addresses_is_an_error() {
+    assert_regex_match!(
+      Batch::change_addresses(vec![None, None], false, false, &mut Vec::new(), || Ok(change(0)))
+        .unwrap_err()
+        .to_string(),
+      "wallet returned change address .* again after 10 attempts; use --allow-change-reuse to allow this"
+    );
+
+    assert_eq!(
+      Batch::change_addresses(vec![None, None], true, false, &mut Vec::new(), || Ok(change(0)))
+        .unwrap(),
+      vec![change(0), change(0)],
+    );
+  }
+
+  #[test]
+  fn requested_change_address_reuse_is_a_warning() {
+    let mut warnings = Vec::new();
+
+    assert_eq!(
+      Batch::change_addresses(
+        vec![None, Some(change(1)), Some(change(1))],
+        false,
+        false,
+        &mut warnings,
+        || Ok(change(0))
+      )
+      .unwrap(),
+      vec![change(0), change(1), change(1)],
+    );
+
+    assert_eq!(
+      warnings,
+      [format!("change address {} is used more than once", change(1))],
+    );
+  }
+
+  #[test]
+  fn requested_change_address_reuse_is_an_error_with_deny_change_reuse() {
+    assert_regex_match!(
+      Batch::change_addresses(
+        vec![None, Some(change(1)), Some(change(1))],
+        false,
+        true,
+        &mut Vec::new(),
+        || Ok(change(0))
+      )
+      .unwrap_err()
+      .to_string(),
+      "change address .* is used more than once"
+    );
+  }
+
+  #[test]
+  fn batch_duplicates_are_counted() {
+    let tempdir = TempDir::new().unwrap();
+
+    let mut entries = Vec::new();
+    let mut inscriptions = Vec::new();
+
+    for (name, contents) in [
+      ("a.txt", "foo".repeat(400)),
+      ("b.txt", "foo".repeat(400)),
+      ("c.txt", "foo".repeat(400)),
+      ("d.txt", "bar".repeat(400)),
+    ] {
+      let file = tempdir.path().join(name);
+      fs::write(&file, &contents).unwrap();
+      entries.push(BatchEntry {
+        file: Some(file),
+        ..Default::default()
+      });
+      inscriptions.push(Inscription::new(
+        Some("text/plain;charset=utf-8".into()),
+        Some(contents.into()),
+      ));
+    }
+
+    let duplicates = Batchfile {
+      inscriptions: entries,
+      ..Default::default()
+    }
+    .duplicates()
+    .unwrap();
+
+    assert_eq!(duplicates, [(1, 0), (2, 0)]);
+
+    // each duplicate drops a 27 byte content type field and a 1200 byte body
+    // pushed in 520, 520 and 160 byte chunks, and adds a 35 byte delegate field
+    assert_eq!(
+      Batchfile::duplicate_savings(&inscriptions, &duplicates),
+      2 * (27 + 1 + 523 + 523 + 162 - 35),
+    );
+  }
+
+  #[test]
+  fn repeated_idempotency_key_returns_cached_output() {
+    let context = Context::builder().chain(Chain::Testnet).build();
+
+    let blocks = context.mine_blocks(2);
+
+    let (port, requests) = mock_http_server(b"FOO");
+
+    let server_config = ServerConfig {
+      chain: Chain::Testnet,
+      idempotency_ttl: Duration::from_secs(60),
+      ..Default::default()
+    };
+
+    let request = serde_json::json!({
+      "idempotency_key": "job",
+      "inscriptions": [{
+        "file": format!("http://127.0.0.1:{port}/foo.txt"),
+        "utxo": OutPoint::new(blocks[0].txdata[0].txid(), 0).to_string(),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [OutPoint::new(blocks[1].txdata[0].txid(), 0).to_string()],
+    });
+
+    let first =
+      Inscribe::inscribe_for_server(request.clone(), &server_config, &context.index).unwrap();
+
+    assert!(first.commit_psbt.is_some());
+
+    let second =
+      Inscribe::inscribe_for_server(request.clone(), &server_config, &context.index).unwrap();
+
+    assert_eq!(
+      serde_json::to_string(&first).unwrap(),
+      serde_json::to_string(&second).unwrap(),
+    );
+
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    let mut request = request;
+    request["fees_utxos"] = serde_json::json!([]);
+
+    assert_eq!(
+      Inscribe::inscribe_for_server(request, &server_config, &context.index)
+        .unwrap_err()
+        .to_string(),
+      "idempotency key job was already used for a different request",
+    );
+
+    assert_eq!(requests.lock().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn idempotency_key_in_progress_is_rejected() {
+    let context = Context::builder().build();
+
+    let path = Inscribe::idempotency_record_path(&context.index, "job");
+
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    let lock = IdempotencyLock::acquire(path.with_extension("lock"), "job").unwrap();
+
+    // the file URL is unreachable, so the request fails once it gets past the lock
+    let request = serde_json::json!({
+      "idempotency_key": "job",
+      "inscriptions": [{
+        "file": "http://127.0.0.1:1/foo.txt",
+        "utxo": outpoint(1).to_string(),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [],
+    });
+
+    assert_eq!(
+      Inscribe::inscribe_for_server(request.clone(), &ServerConfig::default(), &context.index)
+        .unwrap_err()
+        .to_string(),
+      "a request with idempotency key job is already in progress",
+    );
+
+    drop(lock);
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(request, &ServerConfig::default(), &context.index)
+        .unwrap_err()
+        .to_string(),
+      "error fetching .*",
+    );
+  }
+
+  #[test]
+  fn stale_idempotency_lock_is_ignored() {
+    let context = Context::builder().build();
+
+    let path = Inscribe::idempotency_record_path(&context.index, "job");
+
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    // left behind by a process that died long ago
+    fs::write(path.with_extension("lock"), "0").unwrap();
+
+    let request = serde_json::json!({
+      "idempotency_key": "job",
+      "inscriptions": [{
+        "file": "http://127.0.0.1:1/foo.txt",
+        "utxo": outpoint(1).to_string(),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [],
+    });
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(request, &ServerConfig::default(), &context.index)
+        .unwrap_err()
+        .to_string(),
+      "error fetching .*",
+    );
+
+    assert!(!path.with_extension("lock").exists());
+
+    let lock = IdempotencyLock::acquire(path.with_extension("lock"), "job").unwrap();
+
+    assert!(!IdempotencyLock::is_stale(&path.with_extension("lock")));
+
+    drop(lock);
+  }
+
+  #[test]
+  fn expired_idempotency_record_is_discarded() {
+    let context = Context::builder().build();
+
+    let request = sha256::Hash::hash(b"request");
+
+    Inscribe::save_idempotent_output(&context.index, "job", &request, Output::default()).unwrap();
+
+    assert!(
+      Inscribe::load_idempotent_output(&context.index, "job", &request, Duration::from_secs(60))
+        .unwrap()
+        .is_some()
+    );
+
+    assert!(
+      Inscribe::load_idempotent_output(&context.index, "job", &request, Duration::ZERO)
+        .unwrap()
+        .is_none()
+    );
+
+    assert!(
+      Inscribe::load_idempotent_output(&context.index, "job", &request, Duration::from_secs(60))
+        .unwrap()
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn strict_address_network_accepts_matching_destination() {
+    let context = Context::builder().build();
+
+    let request = serde_json::json!({
+      "inscriptions": [{
+        "file": "http://127.0.0.1:1/foo.txt",
+        "utxo": format!("{}", outpoint(1)),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [],
+    });
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(
+        request,
+        &ServerConfig {
+          chain: Chain::Testnet,
+          strict_address_network: true,
+          ..Default::default()
+        },
+        &context.index,
+      )
+      .unwrap_err()
+      .to_string(),
+      "error fetching .*",
+    );
+  }
+
+  #[test]
+  fn strict_address_network_rejects_cross_network_destination() {
+    let context = Context::builder().build();
+
+    let request = serde_json::json!({
+      "inscriptions": [{
+        "file": "http://127.0.0.1:1/foo.txt",
+        "utxo": format!("{}", outpoint(1)),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [],
+    });
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(
+        request.clone(),
+        &ServerConfig {
+          chain: Chain::Mainnet,
+          strict_address_network: true,
+          ..Default::default()
+        },
+        &context.index,
+      )
+      .unwrap_err()
+      .to_string(),
+      "expected `inscriptions\\[0\\].destination` to be valid for mainnet: .*",
+    );
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(
+        request,
+        &ServerConfig {
+          chain: Chain::Mainnet,
+          ..Default::default()
+        },
+        &context.index,
+      )
+      .unwrap_err()
+      .to_string(),
+      "error fetching .*",
+    );
+  }
+
+  #[test]
+  fn broadcast_is_retried_on_transient_errors() {
+    let mut attempts = 0;
+
+    let sent = Batch::send_with_retries(3, Duration::ZERO, txid(1), false, || {
+      attempts += 1;
+      if attempts <= 2 {
+        Err(bitcoincore_rpc::Error::JsonRpc(
+          bitcoincore_rpc::jsonrpc::error::Error::Transport("connection reset".into()),
+        ))
+      } else {
+        Ok(txid(1))
+      }
+    })
+    .unwrap();
+
+    assert_eq!(sent, txid(1));
+    assert_eq!(attempts, 3);
+
+    // a reveal sent right after its commit may find the commit missing
+    for message in ["bad-txns-inputs-missingorspent", "Missing inputs"] {
+      let mut attempts = 0;
+
+      let sent = Batch::send_with_retries(3, Duration::ZERO, txid(1), true, || {
+        attempts += 1;
+        if attempts <= 2 {
+          Err(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::error::Error::Rpc(
+              bitcoincore_rpc::jsonrpc::error::RpcError {
+                code: -25,
+                message: message.into(),
+                data: None,
+              },
+            ),
+          ))
+        } else {
+          Ok(txid(1))
+        }
+      })
+      .unwrap();
+
+      assert_eq!(sent, txid(1));
+      assert_eq!(attempts, 3);
+    }
+  }
+
+  #[test]
+  fn broadcast_is_not_retried_on_permanent_errors() {
+    let mut attempts = 0;
+
+    let err = Batch::send_with_retries(3, Duration::ZERO, txid(1), false, || {
+      attempts += 1;
+      Err(bitcoincore_rpc::Error::JsonRpc(
+        bitcoincore_rpc::jsonrpc::error::Error::Rpc(bitcoincore_rpc::jsonrpc::error::RpcError {
+          code: -26,
+          message: "min relay fee not met".into(),
+          data: None,
+        }),
+      ))
+    })
+    .unwrap_err();
+
+    assert_eq!(attempts, 1);
+    assert_regex_match!(err.to_string(), ".*min relay fee not met.*");
+  }
+
+  #[test]
+  fn broadcast_returns_last_error_when_retries_are_exhausted() {
+    let mut attempts = 0;
+
+    let err = Batch::send_with_retries(2, Duration::ZERO, txid(1), false, || {
+      attempts += 1;
+      Err(bitcoincore_rpc::Error::JsonRpc(
+        bitcoincore_rpc::jsonrpc::error::Error::Rpc(bitcoincore_rpc::jsonrpc::error::RpcError {
+          code: -28,
+          message: "Loading block index...".into(),
+          data: None,
+        }),
+      ))
+    })
+    .unwrap_err();
+
+    assert_eq!(attempts, 3);
+    assert_regex_match!(err.to_string(), ".*Loading block index.*");
+  }
+
+  #[test]
+  fn broadcast_is_not_retried_on_missing_inputs() {
+    for message in ["bad-txns-inputs-missingorspent", "Missing inputs"] {
+      let mut attempts = 0;
+
+      let err = Batch::send_with_retries(3, Duration::ZERO, txid(1), false, || {
+        attempts += 1;
+        Err(bitcoincore_rpc::Error::JsonRpc(
+          bitcoincore_rpc::jsonrpc::error::Error::Rpc(bitcoincore_rpc::jsonrpc::error::RpcError {
+            code: -25,
+            message: message.into(),
+            data: None,
+          }),
+        ))
+      })
+      .unwrap_err();
+
+      assert_eq!(attempts, 1);
+      assert_regex_match!(err.to_string(), format!(".*{message}.*"));
+    }
+  }
+
+  #[test]
+  fn broadcast_of_already_known_transaction_is_success() {
+    for (code, message) in [
+      (-27, "Transaction already in block chain"),
+      (-26, "txn-already-known"),
+      (-26, "txn-already-in-mempool"),
+    ] {
+      let mut attempts = 0;
+
+      let sent = Batch::send_with_retries(3, Duration::ZERO, txid(2), false, || {
+        attempts += 1;
+        if attempts == 1 {
+          Err(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::error::Error::Transport("connection reset".into()),
+          ))
+        } else {
+          Err(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::error::Error::Rpc(
+              bitcoincore_rpc::jsonrpc::error::RpcError {
+                code,
+                message: message.into(),
+                data: None,
+              },
+            ),
+          ))
+        }
+      })
+      .unwrap();
+
+      assert_eq!(sent, txid(2));
+      assert_eq!(attempts, 2);
+    }
+  }
+
+  #[test]
+  fn fetch_headers_are_sent() {
+    let (port, requests) = mock_http_server(b"FOO");
+
+    let headers = Inscribe::fetch_headers(Some(&serde_json::json!({
+      "User-Agent": "custom-agent",
+      "X-Api-Key": "secret",
+    })))
+    .unwrap();
+
+    let client = reqwest::blocking::Client::builder()
+      .default_headers(headers)
+      .build()
+      .unwrap();
+
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("foo.txt");
+
+    assert_eq!(
+      Inscribe::fetch_url_into_file(&client, &format!("http://127.0.0.1:{port}/foo.txt"), &path)
+        .unwrap(),
+      3
+    );
+    assert_eq!(fs::read_to_string(path).unwrap(), "FOO");
+
+    let request = requests.lock().unwrap()[0].to_lowercase();
+    assert!(request.contains("user-agent: custom-agent\r\n"));
+    assert!(!request.contains("ord inscribe endpoint"));
+    assert!(request.contains("x-api-key: secret\r\n"));
+  }
+
+  #[test]
+  fn unsafe_fetch_headers_are_rejected() {
+    assert_eq!(
+      Inscribe::fetch_headers(Some(&serde_json::json!({ "Host": "example.com" })))
+        .unwrap_err()
+        .to_string(),
+      "header `host` can't be set in `fetch_headers`",
+    );
+
+    assert_eq!(
+      Inscribe::fetch_headers(Some(&serde_json::json!({ "X-Big": "a".repeat(MAX_FETCH_HEADERS_SIZE) })))
+        .unwrap_err()
+        .to_string(),
+      format!("`fetch_headers` exceed {MAX_FETCH_HEADERS_SIZE} bytes"),
+    );
+
+    assert_eq!(
+      Inscribe::fetch_headers(None).unwrap()[USER_AGENT],
+      "ord inscribe endpoint",
+    );
+  }
+
+  #[test]
+  fn local_files_are_copied_from_inside_base_directory() {
+    let tempdir = TempDir::new().unwrap();
+    let base = tempdir.path().join("base");
+    fs::create_dir(&base).unwrap();
+    fs::write(base.join("foo.txt"), "FOO").unwrap();
+    fs::write(tempdir.path().join("secret.txt"), "SECRET").unwrap();
+
+    let destination = tempdir.path().join("0.txt");
+
+    assert_eq!(
+      Inscribe::copy_local_file(Some(&base), &base.join("foo.txt"), &destination).unwrap(),
+      3
+    );
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "FOO");
+
+    assert_regex_match!(
+      Inscribe::copy_local_file(Some(&base), &base.join("../secret.txt"), &destination)
+        .unwrap_err()
+        .to_string(),
+      "`.*secret.txt` is outside of `.*base`",
+    );
+
+    assert_eq!(
+      Inscribe::copy_local_file(None, &base.join("foo.txt"), &destination)
+        .unwrap_err()
+        .to_string(),
+      "local files are disabled; start the server with `--inscribe-file-base` to allow them",
+    );
+  }
+
+  #[test]
+  fn oversized_local_inscription_files_are_rejected_and_removed() {
+    let tempdir = TempDir::new().unwrap();
+    let destination = tempdir.path().join("0.txt");
+
+    fs::write(
+      tempdir.path().join("big.txt"),
+      vec![0; MAX_INSCRIPTION_FETCH_SIZE as usize],
+    )
+    .unwrap();
+    fs::write(
+      tempdir.path().join("bigger.txt"),
+      vec![0; MAX_INSCRIPTION_FETCH_SIZE as usize + 1],
+    )
+    .unwrap();
+
+    assert_eq!(
+      Inscribe::copy_local_file(Some(tempdir.path()), &tempdir.path().join("big.txt"), &destination)
+        .unwrap(),
+      MAX_INSCRIPTION_FETCH_SIZE,
+    );
+
+    assert_regex_match!(
+      Inscribe::copy_local_file(
+        Some(tempdir.path()),
+        &tempdir.path().join("bigger.txt"),
+        &destination
+      )
+      .unwrap_err()
+      .to_string(),
+      format!("`.*bigger.txt` exceeds {MAX_INSCRIPTION_FETCH_SIZE} bytes"),
+    );
+    assert!(!destination.exists());
+  }
+
+  #[test]
+  fn file_urls_outside_base_directory_are_rejected() {
+    let context = Context::builder().build();
+
+    let tempdir = TempDir::new().unwrap();
+    let base = tempdir.path().join("base");
+    fs::create_dir(&base).unwrap();
+    fs::write(tempdir.path().join("secret.txt"), "SECRET").unwrap();
+
+    let request = serde_json::json!({
+      "inscriptions": [{
+        "file": Url::from_file_path(tempdir.path().join("secret.txt")).unwrap().to_string(),
+        "utxo": format!("{}", outpoint(1)),
+        "destination": recipient().to_string(),
+      }],
+      "fees_utxos": [],
+    });
+
+    assert_regex_match!(
+      Inscribe::inscribe_for_server(
+        request,
+        &ServerConfig {
+          chain: Chain::Testnet,
+          inscribe_file_base: Some(base),
+          ..Default::default()
+        },
+        &context.index,
+      )
+      .unwrap_err()
+      .to_string(),
+      "error fetching inscription 0 from `file://.*secret.txt`: `.*secret.txt` is outside of `.*base`",
+    );
+  }
+
+  #[test]
+  fn calculate_fee_errors_when_outputs_exceed_inputs() {
+    let tx = Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: vec![TxIn {
+        previous_output: outpoint(1),
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+        witness: Witness::new(),
+      }],
+      output: vec![TxOut {
+        script_pubkey: recipient().script_pubkey(),
+        value: 2_000,
+      }],
+    };
+
+    assert_eq!(
+      Batch::calculate_fee(&tx, &[(outpoint(1), Amount::from_sat(3_000))].into_iter().collect())
+        .unwrap(),
+      1_000
+    );
+
+    assert_eq!(
+      Batch::calculate_fee(&tx, &[(outpoint(1), Amount::from_sat(1_000))].into_iter().collect())
+        .unwrap_err()
+        .to_string(),
+      "transaction outputs (2000) exceed inputs (1000)",
+    );
+  }
+
+  #[test]
+  fn calculate_fee_errors_on_missing_prevout() {
+    let tx = Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: vec![TxIn {
+        previous_output: outpoint(1),
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+        witness: Witness::new(),
+      }],
+      output: Vec::new(),
+    };
+
+    assert_eq!(
+      Batch::calculate_fee(&tx, &BTreeMap::new())
+        .unwrap_err()
+        .to_string(),
+      format!("value of transaction input {} is unknown", outpoint(1)),
+    );
+  }
+
+  #[test]
+  fn batch_sats_are_assigned_to_separate_outputs() {
+    let context = Context::builder().arg("--index-sats").build();
+
+    let first = context.mine_blocks(1)[0].txdata[0].txid();
+    let second = context.mine_blocks(1)[0].txdata[0].txid();
+
+    let utxos = [first, second]
+      .into_iter()
+      .map(|txid| (OutPoint::new(txid, 0), Amount::from_sat(50 * COIN_VALUE)))
+      .collect::<BTreeMap<OutPoint, Amount>>();
+
+    let mut batchfile = Batchfile {
+      inscriptions: vec![BatchEntry::default(), BatchEntry::default()],
+      mode: Mode::SeparateOutputs,
+      sats: Some(vec![Sat(100 * COIN_VALUE), Sat(50 * COIN_VALUE)]),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      batchfile
+        .assign_sats(&context.index, &utxos, &BTreeSet::new(), false)
+        .unwrap(),
+      utxos.keys().cloned().collect::<Vec<OutPoint>>(),
+    );
+
+    assert_eq!(batchfile.inscriptions[0].utxo, Some(OutPoint::new(second, 0)));
+    assert_eq!(batchfile.inscriptions[1].utxo, Some(OutPoint::new(first, 0)));
+
+    let mut batchfile = Batchfile {
+      inscriptions: vec![BatchEntry::default()],
+      mode: Mode::SeparateOutputs,
+      sats: Some(vec![Sat(50 * COIN_VALUE + 1)]),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      batchfile
+        .assign_sats(&context.index, &utxos, &BTreeSet::new(), false)
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "sat `{}` is at offset 1 of output {first}:0; it must be the first sat of its output to be used in `sats`",
+        50 * COIN_VALUE + 1
+      ),
+    );
+
+    let mut batchfile = Batchfile {
+      inscriptions: vec![BatchEntry::default(), BatchEntry::default()],
+      mode: Mode::SeparateOutputs,
+      sats: Some(vec![Sat(50 * COIN_VALUE)]),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      batchfile
+        .assign_sats(&context.index, &utxos, &BTreeSet::new(), false)
+        .unwrap_err()
+        .to_string(),
+      "`sats` contains 1 sats but batch contains 2 inscriptions",
+    );
+  }
+
+  #[test]
+  fn batch_sats_must_be_in_unlocked_wallet_outputs() {
+    let context = Context::builder().arg("--index-sats").build();
+
+    let outpoint = OutPoint::new(context.mine_blocks(1)[0].txdata[0].txid(), 0);
+
+    let batchfile = Batchfile {
+      inscriptions: vec![BatchEntry::default()],
+      mode: Mode::SeparateOutputs,
+      sats: Some(vec![Sat(50 * COIN_VALUE)]),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      batchfile
+        .clone()
+        .assign_sats(&context.index, &BTreeMap::new(), &BTreeSet::new(), false)
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "sat `{}` is in output {outpoint}, which is not an unspent output in the wallet",
+        50 * COIN_VALUE
+      ),
+    );
+
+    let utxos = [(outpoint, Amount::from_sat(50 * COIN_VALUE))]
+      .into_iter()
+      .collect::<BTreeMap<OutPoint, Amount>>();
+
+    assert_eq!(
+      batchfile
+        .clone()
+        .assign_sats(&context.index, &utxos, &[outpoint].into(), false)
+        .unwrap_err()
+        .to_string(),
+      format!("sat `{}` is in output {outpoint}, which is locked", 50 * COIN_VALUE),
+    );
+
+    // without a wallet there are no wallet utxos to check against
+    assert_eq!(
+      batchfile
+        .clone()
+        .assign_sats(&context.index, &BTreeMap::new(), &BTreeSet::new(), true)
+        .unwrap(),
+      vec![outpoint],
+    );
+  }
+
+  #[test]
+  fn json_metadata_from_url_matches_file() {
+    let json = br#"{"foo":"bar","baz":[1,2]}"#;
+
+    let (port, _requests) = mock_http_server(json);
+
+    let from_url = Inscribe::parse_metadata(
+      None,
+      Some(format!("http://127.0.0.1:{port}/metadata.json").into()),
+    )
+    .unwrap();
+
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("metadata.json");
+    fs::write(&path, json).unwrap();
+
+    let from_file = Inscribe::parse_metadata(None, Some(path)).unwrap();
+
+    assert!(from_url.is_some());
+    assert_eq!(from_url, from_file);
+  }
+
+  #[test]
+  fn recovery_key_fingerprint_is_deterministic_and_not_secret() {
+    let secp256k1 = Secp256k1::new();
+    let secret_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+    let recovery_key_pair =
+      UntweakedKeyPair::from_secret_key(&secp256k1, &secret_key).tap_tweak(&secp256k1, None);
+
+    let fingerprint = Batch::recovery_key_fingerprint(recovery_key_pair);
+
+    assert_eq!(fingerprint, Batch::recovery_key_fingerprint(recovery_key_pair));
+    assert_regex_match!(fingerprint, "[0-9a-f]{40}");
+
+    let wif = PrivateKey::new(secret_key, Network::Regtest).to_wif();
+    assert!(!fingerprint.contains(&wif));
+    assert_ne!(fingerprint, hex::encode(secret_key.secret_bytes()));
+  }
+
+  #[test]
+  fn same_sat_offset_near_postage_boundary_warns_or_errors() {
+    let dust = Amount::from_sat(330);
+
+    assert_eq!(
+      Batch::check_sat_offset(0, Amount::from_sat(10_000), dust, true).unwrap(),
+      None,
+    );
+
+    assert_eq!(
+      Batch::check_sat_offset(9_800, Amount::from_sat(10_000), dust, false).unwrap(),
+      Some("sat offset 9800 is within 330 sats of the end of the 10000 sat inscription output".into()),
+    );
+
+    assert_eq!(
+      Batch::check_sat_offset(9_800, Amount::from_sat(10_000), dust, true)
+        .unwrap_err()
+        .to_string(),
+      "sat offset 9800 is within 330 sats of the end of the 10000 sat inscription output; increase postage or drop --preserve-sat-offset",
+    );
+
+    assert_eq!(
+      Batch::check_sat_offset(10_000, Amount::from_sat(10_000), dust, false)
+        .unwrap_err()
+        .to_string(),
+      "sat offset 10000 is outside of the 10000 sat inscription output; increase postage",
+    );
+  }
+
+  #[test]
+  fn same_sat_offset_is_traced_from_satpoint() {
+    let commit_address = recipient();
+
+    let commit_tx = Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: vec![tx_in(outpoint(1)), tx_in(outpoint(2))],
+      output: vec![tx_out(500, change(0)), tx_out(20_500, commit_address.clone())],
+    };
+
+    let utxos = BTreeMap::from([
+      (outpoint(1), Amount::from_sat(1_000)),
+      (outpoint(2), Amount::from_sat(20_000)),
+    ]);
+
+    assert_eq!(
+      Batch::commit_output_offset(&commit_tx, &commit_address, &utxos, satpoint(2, 9_800)).unwrap(),
+      1_000 + 9_800 - 500,
+    );
+
+    assert_eq!(
+      Batch::commit_output_offset(&commit_tx, &commit_address, &utxos, satpoint(1, 100))
+        .unwrap_err()
+        .to_string(),
+      format!("sat at {} isn't sent to the commit output", satpoint(1, 100)),
+    );
+  }
+
+  #[test]
+  fn same_sat_offset_ignores_inscription_pointer() {
+    let context = Context::builder().build();
+
+    let batch = |pointer: Option<u64>| Batch {
+      satpoint: Some(satpoint(1, 9_800)),
+      inscriptions: vec![Inscription {
+        pointer: pointer.map(Inscription::pointer_value),
+        ..inscription("text/plain", "ord")
+      }],
+      destinations: vec![recipient()],
+      commit_fee_rate: FeeRate::try_from(1.0).unwrap(),
+      reveal_fee_rate: FeeRate::try_from(1.0).unwrap(),
+      postage: Amount::from_sat(294),
+      mode: Mode::SameSat,
+      preserve_sat_offset: true,
+      ..Default::default()
+    };
+
+    // the commit aligns the sat to the start of its output, so its offset is
+    // 0 wherever the satpoint and pointer put it
+    for pointer in [None, Some(200)] {
+      assert_eq!(
+        batch(pointer)
+          .create_batch_inscription_transactions(
+            BTreeMap::new(),
+            &context.index,
+            Chain::Mainnet,
+            BTreeSet::new(),
+            BTreeSet::new(),
+            [
+              (outpoint(1), Amount::from_sat(20_000)),
+              (outpoint(2), Amount::from_sat(20_000)),
+            ]
+            .into_iter()
+            .collect(),
+            Some([change(0), change(1)]),
+            None,
+            context.index.client(),
+          )
+          .unwrap_err()
+          .to_string(),
+        "sat offset 0 is within 294 sats of the end of the 294 sat inscription output; increase postage or drop --preserve-sat-offset",
+      );
+    }
+  }
+
+  #[test]
+  fn no_wallet_parent_not_known_to_bitcoind_needs_its_value() {
+    let context = Context::builder().chain(Chain::Testnet).build();
+
+    let parent_info = |value: Option<Amount>| {
+      Inscribe::get_parent_info(
+        Some(inscription_id(1)),
+        &context.index,
+        &BTreeMap::new(),
+        context.index.client(),
+        Chain::Testnet,
+        Some(satpoint(1, 0)),
+        true,
+        Some(recipient().to_string().parse().unwrap()),
+        value,
+        false,
+      )
+    };
+
+    assert_eq!(
+      parent_info(None).unwrap_err().to_string(),
+      format!(
+        "parent transaction {} not found; in --no-wallet mode pass the value of the parent output with --parent-value and its address with --parent-destination",
+        outpoint(1).txid,
+      ),
+    );
+
+    let parent_info = parent_info(Some(Amount::from_sat(546))).unwrap().unwrap();
+
+    assert_eq!(parent_info.location, satpoint(1, 0));
+    assert_eq!(parent_info.destination, recipient());
+    assert_eq!(
+      parent_info.tx_out,
+      TxOut {
+        value: 546,
+        script_pubkey: recipient().script_pubkey(),
+      },
+    );
+  }
+
+  #[test]
+  fn estimate_dummy_signatures_match_input_script_type() {
+    use bitcoin::{key::TweakedPublicKey, PublicKey};
+
+    let weight = |script_pubkey: ScriptBuf| {
+      let mut transaction = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![tx_in(outpoint(1))],
+        output: Vec::new(),
+      };
+
+      Batch::add_dummy_signature(&mut transaction.input[0], &script_pubkey)
+        .map(|()| transaction.weight().to_wu())
+    };
+
+    let key = PublicKey::from_str(
+      "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    )
+    .unwrap();
+    let wpubkey_hash = key.wpubkey_hash().unwrap();
+
+    // 204 weight units of unsigned transaction, then the signed input's
+    // script_sig at 4 per byte and its witness, with 2 for the segwit marker
+    assert_eq!(
+      weight(ScriptBuf::new_v1_p2tr_tweaked(
+        TweakedPublicKey::dangerous_assume_tweaked(XOnlyPublicKey::from(key.inner))
+      ))
+      .unwrap(),
+      204 + 2 + 1 + 1 + 64,
+    );
+    assert_eq!(
+      weight(ScriptBuf::new_v0_p2wpkh(&wpubkey_hash)).unwrap(),
+      204 + 2 + 1 + 1 + 72 + 1 + 33,
+    );
+    assert_eq!(
+      weight(ScriptBuf::new_p2sh(&ScriptBuf::new_v0_p2wpkh(&wpubkey_hash).script_hash())).unwrap(),
+      204 + 4 * 23 + 2 + 1 + 1 + 72 + 1 + 33,
+    );
+    assert_eq!(
+      weight(ScriptBuf::new_p2pkh(&key.pubkey_hash())).unwrap(),
+      204 + 4 * (1 + 72 + 1 + 33),
+    );
+    assert_eq!(
+      weight(ScriptBuf::new_v0_p2wsh(&ScriptBuf::new().wscript_hash()))
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "can't estimate the signed size of input {} with script {}",
+        outpoint(1),
+        ScriptBuf::new_v0_p2wsh(&ScriptBuf::new().wscript_hash()),
+      ),
+    );
+  }
+
+  #[test]
+  fn fee_selection_orders_fee_utxos() {
+    let values = BTreeMap::from([
+      (outpoint(1), 5_000),
+      (outpoint(2), 20_000),
+      (outpoint(3), 1_000),
+      (outpoint(4), 20_000),
+    ]);
+
+    let listed = vec![outpoint(1), outpoint(2), outpoint(3), outpoint(4)];
+
+    for (fee_selection, expected) in [
+      ("as-listed", listed.clone()),
+      (
+        "largest-first",
+        vec![outpoint(2), outpoint(4), outpoint(1), outpoint(3)],
+      ),
+      (
+        "smallest-first",
+        vec![outpoint(3), outpoint(1), outpoint(2), outpoint(4)],
+      ),
+    ] {
+      let mut fees = listed.clone();
+      Inscribe::order_fee_utxos(
+        &mut fees,
+        fee_selection.parse().unwrap(),
+        |outpoint| Ok(values[&outpoint]),
+      )
+      .unwrap();
+      assert_eq!(fees, expected, "{fee_selection}");
+    }
+
+    assert_eq!(
+      "biggest-first".parse::<FeeSelection>().unwrap_err().to_string(),
+      "invalid fee selection `biggest-first`, expected `largest-first`, `smallest-first` or `as-listed`",
+    );
+  }
+
+  #[test]
+  fn finalize_reveal_psbt_uses_external_parent_signature() {
+    let reveal_tx = Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: vec![
+        TxIn {
+          previous_output: outpoint(1),
+          ..Default::default()
+        },
+        TxIn {
+          previous_output: outpoint(2),
+          ..Default::default()
+        },
+      ],
+      output: vec![TxOut {
+        value: 10_000,
+        script_pubkey: recipient().script_pubkey(),
+      }],
+    };
+
+    let mut psbt = Psbt::from_unsigned_tx(reveal_tx.clone()).unwrap();
+
+    let commit_witness = Witness::from_slice(&[vec![1; 64], vec![2; 3]]);
+    psbt.inputs[1].final_script_witness = Some(commit_witness.clone());
+
+    assert_eq!(
+      Batch::finalize_reveal_psbt(psbt.clone()).unwrap_err().to_string(),
+      "input 0 of reveal psbt isn't signed",
+    );
+
+    let signature = Signature {
+      sig: secp256k1::schnorr::Signature::from_slice(&[3; SCHNORR_SIGNATURE_SIZE]).unwrap(),
+      hash_ty: TapSighashType::Default,
+    };
+    psbt.inputs[0].tap_key_sig = Some(signature);
+
+    let finalized = Batch::finalize_reveal_psbt(psbt).unwrap();
+
+    assert_eq!(finalized.txid(), reveal_tx.txid());
+    assert_eq!(finalized.input[0].witness, Witness::from_slice(&[signature.to_vec()]));
+    assert_eq!(finalized.input[1].witness, commit_witness);
+  }
+
+  #[test]
+  fn batch_pointers_are_validated_against_postage() {
+    let entry = |pointer: Option<u64>| BatchEntry {
+      metaprotocol: Some("test".into()),
+      pointer,
+      ..Default::default()
+    };
+
+    let build = |mode: Mode, entries: Vec<BatchEntry>, allow_unbound: bool| {
+      Batchfile {
+        inscriptions: entries,
+        mode,
+        ..Default::default()
+      }
+      .build_inscriptions(
+        Chain::Regtest,
+        Some(1_000),
+        None,
+        Amount::from_sat(10_000),
+        false,
+        false,
+        allow_unbound,
+        false,
+        &BTreeMap::new(),
+      )
+    };
+
+    let inscriptions = build(
+      Mode::SharedOutput,
+      vec![entry(None), entry(None), entry(None)],
+      false,
+    )
+    .unwrap();
+
+    assert_eq!(
+      inscriptions
+        .iter()
+        .map(|inscription| inscription.pointer())
+        .collect::<Vec<Option<u64>>>(),
+      vec![None, Some(11_000), Some(21_000)],
+    );
+
+    assert_eq!(
+      build(Mode::SharedOutput, vec![entry(None), entry(Some(31_000))], false)
+        .unwrap_err()
+        .to_string(),
+      "pointer 31000 of inscription 1 is outside of its output, which spans sats 1000 to 21000; use --allow-unbound to allow this",
+    );
+
+    assert_eq!(
+      build(Mode::SeparateOutputs, vec![entry(Some(500)), entry(None)], false)
+        .unwrap_err()
+        .to_string(),
+      "pointer 500 of inscription 0 is outside of its output, which spans sats 1000 to 11000; use --allow-unbound to allow this",
+    );
+
+    assert_eq!(
+      build(Mode::SharedOutput, vec![entry(None), entry(Some(31_000))], true)
+        .unwrap()[1]
+        .pointer(),
+      Some(31_000),
+    );
+
+    build(
+      Mode::SameSat,
+      vec![entry(None), entry(None), entry(None)],
+      false,
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn failed_inscription_download_removes_fetched_files() {
+    let tempdir = TempDir::new().unwrap();
+    let base = tempdir.path().join("base");
+    let downloads = tempdir.path().join("downloads");
+    fs::create_dir(&base).unwrap();
+    fs::create_dir(&downloads).unwrap();
+    fs::write(base.join("foo.txt"), "FOO").unwrap();
+    fs::write(base.join("baz.txt"), "BAZ").unwrap();
+
+    let client = reqwest::blocking::Client::new();
+
+    assert_regex_match!(
+      Inscribe::fetch_inscription_files(
+        &client,
+        Some(&base),
+        &[
+          (
+            "foo.txt".into(),
+            Some(base.join("foo.txt")),
+            downloads.join("0.txt"),
+          ),
+          (
+            "http://127.0.0.1:1/bar.txt".into(),
+            None,
+            downloads.join("1.txt"),
+          ),
+          (
+            "baz.txt".into(),
+            Some(base.join("baz.txt")),
+            downloads.join("2.txt"),
+          ),
+        ],
+      )
+      .unwrap_err()
+      .to_string(),
+      "error fetching inscription 1 from `http://127.0.0.1:1/bar.txt`: .*",
+    );
+
+    assert_eq!(fs::read_dir(&downloads).unwrap().count(), 0);
+  }
 }
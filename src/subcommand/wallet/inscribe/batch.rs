@@ -1,15 +1,68 @@
-use super::*;
+use {super::*, std::collections::btree_map};
+
+const MAX_CHANGE_ADDRESS_ATTEMPTS: usize = 10;
+
+const BROADCAST_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub(super) struct FeeEstimate {
+  pub(super) commit_fee: u64,
+  pub(super) reveal_fee: u64,
+  pub(super) reveal_vsize: u64,
+}
+
+#[derive(Default)]
+struct OutputFields {
+  commit: Option<Txid>,
+  commit_address: Option<Address>,
+  commit_hex: Option<String>,
+  commit_psbt: Option<String>,
+  inscriptions: Vec<Inscription>,
+  message: Option<String>,
+  recovery_descriptor: Option<String>,
+  reveal: Option<Txid>,
+  reveal_hex: Option<String>,
+  reveal_psbt: Option<String>,
+  total_fees: u64,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct BatchTransactions {
+  pub(super) commit_address: Option<Address>,
+  pub(super) commit_tx: Option<Transaction>,
+  pub(super) dummy_commit_psbt: Option<String>,
+  pub(super) fee_estimate: Option<FeeEstimate>,
+  pub(super) handoff: Option<super::Handoff>,
+  pub(super) key: Option<String>,
+  pub(super) recovery_key_pair: Option<TweakedKeyPair>,
+  pub(super) reveal_tx: Option<Transaction>,
+  pub(super) total_fees: Option<u64>,
+  pub(super) warnings: Vec<String>,
+}
 
 pub(super) struct Batch {
+  pub(super) allow_change_reuse: bool,
+  pub(super) allow_spend_runic: bool,
+  pub(super) broadcast_retries: u32,
   pub(super) commit_fee_rate: FeeRate,
+  pub(super) commit_input: Vec<OutPoint>,
+  pub(super) commit_locktime: u32,
   pub(super) commit_only: bool,
+  pub(super) commit_output_margin: Amount,
+  pub(super) commit_tx_version: i32,
   pub(super) commit_vsize: Option<u64>,
   pub(super) commitment: Option<OutPoint>,
   pub(super) commitment_output: Option<GetRawTransactionResultVout>,
+  pub(super) deny_change_reuse: bool,
   pub(super) destinations: Vec<Address>,
   pub(super) dump: bool,
+  pub(super) dump_json: Option<PathBuf>,
   pub(super) dry_run: bool,
+  pub(super) estimate: bool,
+  pub(super) extra_outputs: Vec<TxOut>,
+  pub(super) fee_utxo_only: bool,
   pub(super) fee_utxos: Vec<OutPoint>,
+  pub(super) handoff: Option<PathBuf>,
   pub(super) inscribe_on_specific_utxos: bool,
   pub(super) inscriptions: Vec<Inscription>,
   pub(super) key: Option<String>,
@@ -18,29 +71,51 @@ pub(super) struct Batch {
   pub(super) no_backup: bool,
   pub(super) no_broadcast: bool,
   pub(super) no_limit: bool,
+  pub(super) no_rbf: bool,
   pub(super) no_wallet: bool,
   pub(super) parent_info: Option<ParentInfo>,
   pub(super) postage: Amount,
+  pub(super) prefer_utxo: Vec<OutPoint>,
+  pub(super) preserve_sat_offset: bool,
+  pub(super) psbt_package: bool,
   pub(super) reinscribe: bool,
+  pub(super) reveal_change: Option<Address>,
   pub(super) reveal_fee: Option<Amount>,
   pub(super) reveal_fee_rate: FeeRate,
   pub(super) reveal_input: Vec<OutPoint>,
+  pub(super) reveal_parent_internal_key: Option<XOnlyPublicKey>,
+  pub(super) reveal_parent_psbt: bool,
   pub(super) reveal_psbt: Option<Psbt>,
+  pub(super) reveal_tx_version: i32,
   pub(super) satpoint: Option<SatPoint>,
+  pub(super) weight_warning_percent: u64,
 }
 
 impl Default for Batch {
   fn default() -> Batch {
     Batch {
+      allow_change_reuse: false,
+      allow_spend_runic: false,
+      broadcast_retries: 3,
       commit_fee_rate: 1.0.try_into().unwrap(),
+      commit_input: Vec::new(),
+      commit_locktime: 0,
       commit_only: false,
+      commit_output_margin: Amount::ZERO,
+      commit_tx_version: 2,
       commit_vsize: None,
       commitment: None,
       commitment_output: None,
+      deny_change_reuse: false,
       destinations: Vec::new(),
       dump: false,
+      dump_json: None,
       dry_run: false,
+      estimate: false,
+      extra_outputs: Vec::new(),
+      fee_utxo_only: false,
       fee_utxos: Vec::new(),
+      handoff: None,
       inscribe_on_specific_utxos: false,
       inscriptions: Vec::new(),
       key: None,
@@ -49,15 +124,24 @@ impl Default for Batch {
       no_backup: false,
       no_broadcast: false,
       no_limit: false,
+      no_rbf: false,
       no_wallet: false,
       parent_info: None,
       postage: Amount::from_sat(10_000),
+      prefer_utxo: Vec::new(),
+      preserve_sat_offset: false,
+      psbt_package: false,
       reinscribe: false,
+      reveal_change: None,
       reveal_fee: None,
       reveal_fee_rate: 1.0.try_into().unwrap(),
       reveal_input: Vec::new(),
+      reveal_parent_internal_key: None,
+      reveal_parent_psbt: false,
       reveal_psbt: None,
+      reveal_tx_version: 2,
       satpoint: None,
+      weight_warning_percent: 90,
     }
   }
 }
@@ -71,7 +155,6 @@ impl Batch {
     locked_utxos: &BTreeSet<OutPoint>,
     runic_utxos: BTreeSet<OutPoint>,
     utxos: &mut BTreeMap<OutPoint, Amount>,
-    force_input: Vec<OutPoint>,
     change: Option<Address>,
   ) -> Result<Output> {
     let use_psbt_for_commit = true; // when not signing the commit, should we use psbt or hex for the unsigned commit tx?
@@ -85,53 +168,114 @@ impl Batch {
       if self.commit_fee_rate != FeeRate::try_from(0.0)? {
         return Err(anyhow!("don't use `--commit-fee-rate` when using specific utxos to pay fees; the rate will be calculated from the size of the fee utxo(s)"));
       }
-      if !force_input.is_empty() {
+      if !self.commit_input.is_empty() {
         return Err(anyhow!("don't use `--commit-input` when using specific utxos to pay fees"));
       }
 
       for outpoint in &self.fee_utxos {
-        if !utxos.contains_key(&outpoint) {
+        if !utxos.contains_key(outpoint) {
           utxos.insert(*outpoint, Amount::from_sat(client.get_raw_transaction(&outpoint.txid, None)?.output[outpoint.vout as usize].value));
         }
       }
     }
 
-    let force_input = if self.fee_utxos.is_empty() {
-      force_input
+    if !self.allow_spend_runic {
+      Self::check_runic_inputs(
+        index,
+        &runic_utxos,
+        &self
+          .force_input()
+          .iter()
+          .chain(&self.reveal_input)
+          .chain(self.inscriptions.iter().filter_map(|inscription| inscription.utxo.as_ref()))
+          .cloned()
+          .collect::<Vec<OutPoint>>(),
+      )?;
+    }
+
+    let mut change_warnings = Vec::new();
+
+    let (commit_tx_change, reveal_change) = if self.no_wallet {
+      (None, self.reveal_change.clone())
     } else {
-      self.fee_utxos.clone()
+      let mut requested = vec![None, change];
+
+      let needs_reveal_change = self.commitment.is_some()
+        && self.next_inscriptions.is_empty()
+        && self.reveal_fee != Some(Amount::from_sat(0));
+
+      if needs_reveal_change {
+        requested.push(self.reveal_change.clone());
+      }
+
+      let mut addresses = Self::change_addresses(
+        requested,
+        self.allow_change_reuse,
+        self.deny_change_reuse,
+        &mut change_warnings,
+        || get_change_address(client, chain),
+      )?
+      .into_iter();
+
+      (
+        Some([addresses.next().unwrap(), addresses.next().unwrap()]),
+        addresses.next(),
+      )
     };
 
-    let commit_tx_change = if self.no_wallet {
-      None
-    } else {
-      Some([
-      get_change_address(client, chain)?,
-      match change {
-        Some(change) => change,
-        None => get_change_address(client, chain)?,
-      },
-    ])};
+    let BatchTransactions {
+      commit_address,
+      commit_tx,
+      dummy_commit_psbt,
+      fee_estimate,
+      handoff,
+      key,
+      recovery_key_pair,
+      reveal_tx,
+      total_fees,
+      mut warnings,
+    } = self.create_batch_inscription_transactions(
+      wallet_inscriptions,
+      index,
+      chain,
+      locked_utxos.clone(),
+      runic_utxos,
+      utxos.clone(),
+      commit_tx_change,
+      reveal_change,
+      client,
+    )?;
 
-    let (commit_tx, reveal_tx, recovery_key_pair, total_fees, dummy_commit_psbt) = self
-      .create_batch_inscription_transactions(
-        wallet_inscriptions,
-        index,
-        chain,
-        locked_utxos.clone(),
-        runic_utxos,
-        utxos.clone(),
-        commit_tx_change,
-        force_input,
-        client,
-      )?;
+    warnings.splice(0..0, change_warnings);
+
+    if let Some(dummy_commit_psbt) = dummy_commit_psbt {
+      return Ok(self.output(
+        OutputFields {
+          commit_psbt: Some(dummy_commit_psbt),
+          message: Some("sign commit_psbt then re-run the /inscribe endpoint with `commit_vsize` in the input JSON set to the vsize of the signed tx; the tx has 0 fees so you can't accidentally broadcast it".to_string()),
+          ..Default::default()
+        },
+        &BTreeMap::new(),
+      ));
+    }
+
+    if let Some(fee_estimate) = fee_estimate {
+      let mut output = self.output(
+        OutputFields {
+          commit_address,
+          total_fees: total_fees.unwrap(),
+          ..Default::default()
+        },
+        utxos,
+      );
 
-    if dummy_commit_psbt.is_some() {
-      let dummy_commit_psbt = dummy_commit_psbt.unwrap();
-      return Ok(self.output(None, None, None,
-                            Some(dummy_commit_psbt),
-                            Some("sign commit_psbt then re-run the /inscribe endpoint with `commit_vsize` in the input JSON set to the vsize of the signed tx; the tx has 0 fees so you can't accidentally broadcast it".to_string()),
-                            None, None, None, 0, Vec::new(), &BTreeMap::new()));
+      output.commit_fee = Some(fee_estimate.commit_fee);
+      output.content_hashes = Vec::new();
+      output.parent = None;
+      output.reveal_fee = Some(fee_estimate.reveal_fee);
+      output.reveal_vsize = Some(fee_estimate.reveal_vsize);
+
+      return Ok(output);
     }
 
     let commit_tx = commit_tx.unwrap();
@@ -140,27 +284,64 @@ impl Batch {
     let total_fees = total_fees.unwrap();
 
     if self.dry_run {
-      return Ok(self.output(
-        if self.commitment.is_some() {
-          None
-        } else {
-          Some(commit_tx.txid())
+      let mut output = self.output(
+        OutputFields {
+          commit: if self.commitment.is_some() {
+            None
+          } else {
+            Some(commit_tx.txid())
+          },
+          commit_address,
+          inscriptions: self.inscriptions.clone(),
+          reveal: if self.commit_only {
+            None
+          } else {
+            Some(reveal_tx.txid())
+          },
+          total_fees,
+          ..Default::default()
         },
-        if self.commit_only {
-          None
-        } else {
-          Some(reveal_tx.txid())
+        utxos,
+      );
+
+      output.commit_inputs = self.commit_inputs(&commit_tx);
+      output.warnings = warnings;
+      output.recovery_key_fingerprint = Some(Self::recovery_key_fingerprint(recovery_key_pair));
+
+      self.add_inscription_sats(index, &commit_tx, &reveal_tx, &mut output)?;
+
+      return Ok(output);
+    }
+
+    if self.psbt_package {
+      let commit_psbt = Self::commit_psbt(index, &commit_tx, &mut warnings)?;
+      let reveal_psbt = self.reveal_psbt(index, &commit_tx, &reveal_tx)?;
+
+      if !self.no_backup && self.key.is_none() && !self.no_wallet {
+        Self::backup_recovery_key(client, recovery_key_pair, chain.network())?;
+      }
+
+      let mut output = self.output(
+        OutputFields {
+          commit: Some(commit_tx.txid()),
+          commit_address,
+          inscriptions: self.inscriptions.clone(),
+          reveal: Some(reveal_tx.txid()),
+          total_fees,
+          ..Default::default()
         },
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        total_fees,
-        self.inscriptions.clone(),
         utxos,
-      ));
+      );
+
+      output.commit_inputs = self.commit_inputs(&commit_tx);
+      output.warnings = warnings;
+      output.recovery_key_fingerprint = Some(Self::recovery_key_fingerprint(recovery_key_pair));
+      output.psbt_package = vec![
+        general_purpose::STANDARD.encode(commit_psbt.serialize()),
+        general_purpose::STANDARD.encode(reveal_psbt.serialize()),
+      ];
+
+      return Ok(output);
     }
 
     let signed_commit_tx = if self.commitment.is_some() || self.no_wallet {
@@ -171,6 +352,54 @@ impl Batch {
       .hex
     };
 
+    if self.reveal_parent_psbt {
+      if self.parent_info.is_none() {
+        bail!("--reveal-parent-psbt requires a parent");
+      }
+
+      let reveal_psbt = self.reveal_psbt(index, &commit_tx, &reveal_tx)?;
+
+      if !self.no_backup && self.key.is_none() {
+        Self::backup_recovery_key(client, recovery_key_pair, chain.network())?;
+      }
+
+      let mut output = self.output(
+        OutputFields {
+          commit: if self.commitment.is_some() {
+            None
+          } else {
+            Some(commit_tx.txid())
+          },
+          commit_address,
+          commit_hex: if self.commitment.is_some() {
+            None
+          } else {
+            Some(signed_commit_tx.raw_hex())
+          },
+          inscriptions: self.inscriptions.clone(),
+          reveal: Some(reveal_tx.txid()),
+          total_fees,
+          ..Default::default()
+        },
+        utxos,
+      );
+
+      output.commit_inputs = self.commit_inputs(&commit_tx);
+      output.warnings = warnings;
+      output.recovery_key_fingerprint = Some(Self::recovery_key_fingerprint(recovery_key_pair));
+      output.message = Some(
+        if self.commitment.is_some() {
+          "sign the parent input of reveal_psbt, then run `ord wallet inscribe --finalize-reveal-psbt <PSBT>` to broadcast the reveal"
+        } else {
+          "broadcast commit_hex, sign the parent input of reveal_psbt, then run `ord wallet inscribe --finalize-reveal-psbt <PSBT>` to broadcast the reveal"
+        }
+        .into(),
+      );
+      output.reveal_psbt = Some(general_purpose::STANDARD.encode(reveal_psbt.serialize()));
+
+      return Ok(output);
+    }
+
     let mut reveal_input_info = Vec::new();
 
     if self.parent_info.is_some() {
@@ -186,7 +415,7 @@ impl Batch {
     }
 
     for input in &self.reveal_input {
-      let output = index.get_transaction(input.txid)?.unwrap().output[input.vout as usize].clone();
+      let output = Self::get_reveal_input_output(index, *input)?;
       reveal_input_info.push(SignRawTransactionInput {
         txid: input.txid,
         vout: input.vout,
@@ -247,8 +476,8 @@ impl Batch {
             return Err(anyhow!("prevout of input {i} of reveal_psbt is incorrect"));
           }
 
-          if reveal_tx.input[i].witness.len() == 0 {
-            if input.witness.len() > 0 {
+          if reveal_tx.input[i].witness.is_empty() {
+            if !input.witness.is_empty() {
               reveal_tx.input[i] = input.clone();
             } else {
               return Err(anyhow!("input {i} of reveal_psbt isn't signed"));
@@ -271,7 +500,7 @@ impl Batch {
         let mut blank_reveal_tx = reveal_tx.clone();
         let mut any_unsigned = false;
         for input in &mut blank_reveal_tx.input {
-          if input.witness.len() == 0 {
+          if input.witness.is_empty() {
             any_unsigned = true;
           }
           input.witness = Witness::new();
@@ -299,22 +528,67 @@ impl Batch {
         }
       };
 
-      return Ok(self.output(None, None, None,
-                            Some(commit_tx_hex),
-                            Some(if self.parent_info.is_none() {
-                              "sign commit_psbt, then broadcast the signed result and reveal_hex"
-                            } else {
-                              "sign commit_psbt and reveal_hex, then broadcast them both. or sign the reveal_psbt, add it to the input json, and run the /inscribe endpoint again"
-                            }.to_string()),
-                            Some(consensus::encode::serialize(&reveal_tx).raw_hex()),
-                            blank_reveal_psbt,
-                            None, 0, Vec::new(), &BTreeMap::new()));
+      let mut output = self.output(
+        OutputFields {
+          commit_address,
+          commit_psbt: Some(commit_tx_hex),
+          message: Some(if self.parent_info.is_none() {
+            "sign commit_psbt, then broadcast the signed result and reveal_hex"
+          } else {
+            "sign commit_psbt and reveal_hex, then broadcast them both. or sign the reveal_psbt, add it to the input json, and run the /inscribe endpoint again"
+          }.to_string()),
+          reveal_hex: Some(consensus::encode::serialize(&reveal_tx).raw_hex()),
+          reveal_psbt: blank_reveal_psbt,
+          ..Default::default()
+        },
+        &BTreeMap::new(),
+      );
+
+      output.commit_inputs = self.commit_inputs(&commit_tx);
+      output.warnings = warnings;
+      output.recovery_key_fingerprint = Some(Self::recovery_key_fingerprint(recovery_key_pair));
+
+      return Ok(output);
     }
 
     if !self.no_backup && self.key.is_none() {
       Self::backup_recovery_key(client, recovery_key_pair, chain.network())?;
     }
 
+    if let Some(path) = &self.dump_json {
+      let inscriptions = if self.commit_only {
+        Vec::new()
+      } else {
+        let txid = consensus::encode::deserialize::<Transaction>(&signed_reveal_tx)?.txid();
+        (0..self.inscriptions.len())
+          .map(|index| InscriptionId {
+            txid,
+            index: index.try_into().unwrap(),
+          })
+          .collect()
+      };
+
+      Self::write_json(
+        path,
+        &super::RecoveryDump {
+          commit_address: commit_address.as_ref().map(|address| address.to_string()),
+          commit_hex: if self.commitment.is_none() {
+            Some(signed_commit_tx.raw_hex())
+          } else {
+            None
+          },
+          inscriptions,
+          key: key.unwrap(),
+          recovery_descriptor: Self::get_recovery_key(client, recovery_key_pair, chain.network())?,
+          reveal_hex: if self.commit_only {
+            None
+          } else {
+            Some(signed_reveal_tx.raw_hex())
+          },
+        },
+      )?;
+    }
+
     let (commit, reveal) = if self.no_broadcast {
       (if self.commitment.is_some() { None }
       	  else { Some(client.decode_raw_transaction(&signed_commit_tx, None)?.txid) },
@@ -324,13 +598,25 @@ impl Batch {
     let commit = if self.commitment.is_some() {
       None
     } else {
-      Some(client.send_raw_transaction(&signed_commit_tx)?)
+      Some(Self::send_with_retries(
+        self.broadcast_retries,
+        BROADCAST_RETRY_BACKOFF,
+        consensus::encode::deserialize::<Transaction>(&signed_commit_tx)?.txid(),
+        false,
+        || client.send_raw_transaction(&signed_commit_tx),
+      )?)
     };
 
     let reveal = if self.commit_only {
       None
     } else {
-    match client.send_raw_transaction(&signed_reveal_tx) {
+    match Self::send_with_retries(
+      self.broadcast_retries,
+      BROADCAST_RETRY_BACKOFF,
+      consensus::encode::deserialize::<Transaction>(&signed_reveal_tx)?.txid(),
+      commit.is_some(),
+      || client.send_raw_transaction(&signed_reveal_tx),
+    ) {
     Ok(txid) => Some(txid),
     Err(err) => {
       return Err(anyhow!(
@@ -343,47 +629,167 @@ impl Batch {
     (commit, reveal)
     };
 
-    Ok(self.output(
-      commit,
-      reveal,
-      if self.dump && self.commitment.is_none() { Some(signed_commit_tx.raw_hex()) } else { None },
-      None, None,
-      if self.dump && !self.commit_only { Some(signed_reveal_tx.raw_hex()) } else { None },
-      None,
-      if self.dump { Some(Self::get_recovery_key(&client, recovery_key_pair, chain.network())?.to_string()) } else { None },
-      total_fees,
-      self.inscriptions.clone(),
+    if let (Some(path), Some(handoff)) = (&self.handoff, handoff) {
+      Self::write_json(path, &handoff)?;
+    }
+
+    let mut output = self.output(
+      OutputFields {
+        commit,
+        commit_address,
+        commit_hex: if self.dump && self.commitment.is_none() { Some(signed_commit_tx.raw_hex()) } else { None },
+        inscriptions: self.inscriptions.clone(),
+        recovery_descriptor: if self.dump { Some(Self::get_recovery_key(client, recovery_key_pair, chain.network())?.to_string()) } else { None },
+        reveal,
+        reveal_hex: if self.dump && !self.commit_only { Some(signed_reveal_tx.raw_hex()) } else { None },
+        total_fees,
+        ..Default::default()
+      },
       utxos,
-    ))
+    );
+
+    output.commit_inputs = self.commit_inputs(&commit_tx);
+    output.warnings = warnings;
+    output.recovery_key_fingerprint = Some(Self::recovery_key_fingerprint(recovery_key_pair));
+
+    self.add_inscription_sats(index, &commit_tx, &reveal_tx, &mut output)?;
+
+    Ok(output)
+  }
+
+  fn commit_inputs(&self, commit_tx: &Transaction) -> Vec<OutPoint> {
+    if self.commitment.is_some() {
+      return Vec::new();
+    }
+
+    commit_tx
+      .input
+      .iter()
+      .map(|input| input.previous_output)
+      .collect()
   }
 
-  fn output(
+  fn add_inscription_sats(
     &self,
-    commit: Option<Txid>,
-    reveal: Option<Txid>,
-    commit_hex: Option<String>,
-    commit_psbt: Option<String>,
-    message: Option<String>,
-    reveal_hex: Option<String>,
-    reveal_psbt: Option<String>,
-    recovery_descriptor: Option<String>,
-    total_fees: u64,
-    inscriptions: Vec<Inscription>,
-    utxos: &BTreeMap<OutPoint, Amount>,
-  ) -> super::Output {
+    index: &Index,
+    commit_tx: &Transaction,
+    reveal_tx: &Transaction,
+    output: &mut super::Output,
+  ) -> Result {
+    if !index.has_sat_index() || output.inscriptions.is_empty() {
+      return Ok(());
+    }
+
+    let mut known = BTreeMap::new();
+
+    if self.commitment.is_none() {
+      let Some(commit_input_sat_ranges) = Self::input_sat_ranges(index, commit_tx, &known)? else {
+        return Ok(());
+      };
+
+      let commit_outpoint = reveal_tx.input[if self.parent_info.is_some() { 1 } else { 0 }].previous_output;
+
+      let start = commit_tx.output[..commit_outpoint.vout as usize]
+        .iter()
+        .map(|output| output.value)
+        .sum::<u64>();
+
+      known.insert(
+        commit_outpoint,
+        Self::slice_sat_ranges(
+          &commit_input_sat_ranges,
+          start,
+          commit_tx.output[commit_outpoint.vout as usize].value,
+        ),
+      );
+    }
+
+    let Some(reveal_input_sat_ranges) = Self::input_sat_ranges(index, reveal_tx, &known)? else {
+      return Ok(());
+    };
+
+    for inscription in &mut output.inscriptions {
+      let start = reveal_tx.output[..inscription.location.outpoint.vout as usize]
+        .iter()
+        .map(|output| output.value)
+        .sum::<u64>()
+        + inscription.location.offset;
+
+      inscription.sat = Self::slice_sat_ranges(&reveal_input_sat_ranges, start, 1)
+        .first()
+        .map(|(start, _end)| Sat(*start));
+    }
+
+    Ok(())
+  }
+
+  fn input_sat_ranges(
+    index: &Index,
+    tx: &Transaction,
+    known: &BTreeMap<OutPoint, Vec<(u64, u64)>>,
+  ) -> Result<Option<Vec<(u64, u64)>>> {
+    let mut sat_ranges = Vec::new();
+
+    for input in &tx.input {
+      match known.get(&input.previous_output) {
+        Some(known) => sat_ranges.extend(known),
+        None => match index.list(input.previous_output)? {
+          Some(List::Unspent(list)) => sat_ranges.extend(list),
+          _ => return Ok(None),
+        },
+      }
+    }
+
+    Ok(Some(sat_ranges))
+  }
+
+  fn slice_sat_ranges(sat_ranges: &[(u64, u64)], mut start: u64, mut len: u64) -> Vec<(u64, u64)> {
+    let mut slice = Vec::new();
+
+    for (range_start, range_end) in sat_ranges {
+      if len == 0 {
+        break;
+      }
+
+      let size = range_end - range_start;
+
+      if start >= size {
+        start -= size;
+        continue;
+      }
+
+      let take = (size - start).min(len);
+      slice.push((range_start + start, range_start + start + take));
+      start = 0;
+      len -= take;
+    }
+
+    slice
+  }
+
+  fn output(&self, fields: OutputFields, utxos: &BTreeMap<OutPoint, Amount>) -> super::Output {
+    let OutputFields {
+      commit,
+      commit_address,
+      commit_hex,
+      commit_psbt,
+      inscriptions,
+      message,
+      recovery_descriptor,
+      reveal,
+      reveal_hex,
+      reveal_psbt,
+      total_fees,
+    } = fields;
+
     if commit_psbt.is_some() {
       return super::Output {
-        commit: None,
-        commit_hex: None,
+        commit_address: commit_address.map(|address| address.to_string()),
         commit_psbt,
-        inscriptions: Vec::new(),
         message,
-        parent: None,
-        recovery_descriptor: None,
-        reveal: None,
         reveal_hex,
         reveal_psbt,
-        total_fees: 0,
+        ..Default::default()
       };
     }
 
@@ -419,6 +825,7 @@ impl Batch {
           outpoint: OutPoint { txid: reveal.unwrap(), vout },
           offset,
         },
+        sat: None,
       });
       }
 
@@ -431,18 +838,27 @@ impl Batch {
       }
     }
 
+    let content_hashes = inscriptions
+      .iter()
+      .map(|inscription| match inscription.delegate() {
+        // delegate inscriptions don't include their body in the envelope
+        Some(delegate) => delegate.to_string(),
+        None => sha256::Hash::hash(inscription.body().unwrap_or_default()).to_string(),
+      })
+      .collect();
+
     super::Output {
       commit,
+      commit_address: commit_address.map(|address| address.to_string()),
       commit_hex,
-      commit_psbt: None,
-      message: None,
+      content_hashes,
+      inscriptions: inscriptions_output,
+      parent: self.parent_info.clone().map(|info| info.id),
+      recovery_descriptor,
       reveal,
       reveal_hex,
-      reveal_psbt: None,
-      recovery_descriptor,
       total_fees,
-      parent: self.parent_info.clone().map(|info| info.id),
-      inscriptions: inscriptions_output,
+      ..Default::default()
     }
   }
 
@@ -455,9 +871,11 @@ impl Batch {
     runic_utxos: BTreeSet<OutPoint>,
     mut utxos: BTreeMap<OutPoint, Amount>,
     change: Option<[Address; 2]>,
-    force_input: Vec<OutPoint>,
+    reveal_change: Option<Address>,
     client: &Client,
-  ) -> Result<(Option<Transaction>, Option<Transaction>, Option<TweakedKeyPair>, Option<u64>, Option<String>)> {
+  ) -> Result<BatchTransactions> {
+    let force_input = self.force_input();
+
     if let Some(parent_info) = &self.parent_info {
       assert!(self
         .inscriptions
@@ -508,16 +926,42 @@ impl Batch {
         .map(|satpoint| satpoint.outpoint)
         .collect::<BTreeSet<OutPoint>>();
 
-      utxos
-        .iter()
-        .find(|(outpoint, amount)| {
-          amount.to_sat() > 0
-            && !inscribed_utxos.contains(outpoint)
-            && !locked_utxos.contains(outpoint)
-            && !runic_utxos.contains(outpoint)
-            && !self.fee_utxos.contains(outpoint)
+      let ineligible = |outpoint: &OutPoint| {
+        if inscribed_utxos.contains(outpoint) {
+          Some("inscribed")
+        } else if locked_utxos.contains(outpoint) {
+          Some("locked")
+        } else if runic_utxos.contains(outpoint) {
+          Some("runic")
+        } else if self.fee_utxos.contains(outpoint) {
+          Some("a fee utxo")
+        } else {
+          None
+        }
+      };
+
+      let preferred = self.prefer_utxo.iter().find(|outpoint| {
+        let reason = match utxos.get(outpoint) {
+          None => Some("not in wallet"),
+          Some(amount) if amount.to_sat() == 0 => Some("empty"),
+          Some(_) => ineligible(outpoint),
+        };
+
+        if let Some(reason) = reason {
+          eprintln!("warning: skipping preferred utxo {outpoint}: {reason}");
+        }
+
+        reason.is_none()
+      });
+
+      preferred
+        .or_else(|| {
+          utxos
+            .iter()
+            .find(|(outpoint, amount)| amount.to_sat() > 0 && ineligible(outpoint).is_none())
+            .map(|(outpoint, _amount)| outpoint)
         })
-        .map(|(outpoint, _amount)| SatPoint {
+        .map(|outpoint| SatPoint {
           outpoint: *outpoint,
           offset: 0,
         })
@@ -585,6 +1029,21 @@ impl Batch {
 
     let commit_tx_address = Address::p2tr_tweaked(taproot_spend_info.output_key(), chain.network());
 
+    // the handoff is only written once the commit has been broadcast
+    let handoff = match &self.handoff {
+      Some(_) if self.commit_only => Some(super::Handoff {
+        commit_address: commit_tx_address.to_string().parse()?,
+        content_hashes: self.content_hashes(),
+        key: PrivateKey::new(key_pair.secret_key(), chain.network()).to_wif(),
+        postage: self.postage.to_sat(),
+      }),
+      Some(path) => {
+        self.check_handoff(path, &key_pair, &commit_tx_address)?;
+        None
+      }
+      None => None,
+    };
+
     let reveal_change_address = if !self.next_inscriptions.is_empty() {
       let next_reveal_script = Inscription::append_batch_reveal_script(
         &self.next_inscriptions,
@@ -600,6 +1059,8 @@ impl Batch {
         .expect("finalizing taproot builder should work");
 
       Some(Address::p2tr_tweaked(next_taproot_spend_info.output_key(), chain.network()))
+    } else if reveal_change.is_some() {
+      reveal_change
     } else if change.is_some() {
       Some(change.clone().unwrap()[0].clone())
     } else {
@@ -656,15 +1117,21 @@ impl Batch {
       );
     }
 
+    reveal_outputs.extend(self.extra_outputs.iter().cloned());
+
+    let extra_outputs_value = self
+      .extra_outputs
+      .iter()
+      .map(|output| Amount::from_sat(output.value))
+      .sum::<Amount>();
+
     let commit_input = if self.parent_info.is_some() { 1 } else { 0 };
 
-    if self.reveal_fee != Some(Amount::from_sat(0)) {
-      if self.commitment.is_some() {
-        reveal_outputs.push(TxOut {
-          script_pubkey: reveal_change_address.unwrap().script_pubkey(),
-          value: 0,
-        });
-      }
+    if self.reveal_fee != Some(Amount::from_sat(0)) && self.commitment.is_some() {
+      reveal_outputs.push(TxOut {
+        script_pubkey: reveal_change_address.unwrap().script_pubkey(),
+        value: 0,
+      });
     }
 
     let (_, mut reveal_fee, reveal_vsize) = Self::build_reveal_transaction(
@@ -674,15 +1141,33 @@ impl Batch {
       commit_input,
       reveal_outputs.clone(),
       &reveal_script,
+      self.no_rbf,
+      self.reveal_tx_version,
     );
 
+    let commit_utxos = if self.fee_utxo_only {
+      let allowed = satpoints
+        .iter()
+        .map(|satpoint| satpoint.outpoint)
+        .chain(force_input.iter().cloned())
+        .collect::<BTreeSet<OutPoint>>();
+
+      utxos
+        .iter()
+        .filter(|(outpoint, _amount)| allowed.contains(outpoint))
+        .map(|(outpoint, amount)| (*outpoint, *amount))
+        .collect()
+    } else {
+      utxos.clone()
+    };
+
     let commit_vsize = if self.fee_utxos.is_empty() {
       0
     } else {
       let dummy_commit_tx = TransactionBuilder::new(
         satpoints.clone(),
         wallet_inscriptions.clone(),
-        utxos.clone(),
+        commit_utxos.clone(),
         locked_utxos.clone(),
         runic_utxos.clone(),
         commit_tx_address.clone(),
@@ -693,13 +1178,30 @@ impl Batch {
         self.no_wallet,
       ).build_transaction()?;
 
-      if self.no_wallet {
+      if self.estimate {
+        let mut dummy_commit_tx = dummy_commit_tx;
+        for input in &mut dummy_commit_tx.input {
+          let outpoint = input.previous_output;
+          let output = index
+            .get_transaction(outpoint.txid)?
+            .ok_or_else(|| anyhow!("commit input {outpoint} not found in index"))?
+            .output
+            .into_iter()
+            .nth(outpoint.vout.try_into().unwrap())
+            .ok_or_else(|| anyhow!("commit input {outpoint} vout out of range"))?;
+          Self::add_dummy_signature(input, &output.script_pubkey)?;
+        }
+        dummy_commit_tx.vsize() as u64
+      } else if self.no_wallet {
         if let Some(commit_vsize) = self.commit_vsize {
           commit_vsize
         } else {
           // todo - can we figure out how big this will be after signing without signing it?
           let dummy_commit_psbt = general_purpose::STANDARD.encode(Psbt::from_unsigned_tx(dummy_commit_tx)?.serialize());
-          return Ok((None, None, None, None, Some(dummy_commit_psbt)));
+          return Ok(BatchTransactions {
+            dummy_commit_psbt: Some(dummy_commit_psbt),
+            ..Default::default()
+          });
         }
       } else {
         let dummy_commit_signed = client.sign_raw_transaction_with_wallet(&dummy_commit_tx, None, None)?;
@@ -709,12 +1211,12 @@ impl Batch {
           }
           bail!("failed to sign dummy commit tx");
         }
-        client.decode_raw_transaction(&dummy_commit_signed.hex, None)?.vsize as u64
+        u64::from(client.decode_raw_transaction(&dummy_commit_signed.hex, None)?.vsize)
       }
     };
 
     if !self.fee_utxos.is_empty() {
-      let fee_utxos_value = self.fee_utxos.iter().map(|outpoint| utxos[&outpoint]).sum::<Amount>();
+      let fee_utxos_value = self.fee_utxos.iter().map(|outpoint| utxos[outpoint]).sum::<Amount>();
       let total_vsize = commit_vsize + reveal_vsize;
       // eprintln!("total_vsize {} = commit_vsize {} + reveal_vsize {}", total_vsize, commit_vsize, reveal_vsize);
       reveal_fee = (fee_utxos_value * reveal_vsize + Amount::from_sat(total_vsize - 1)) / total_vsize;
@@ -729,6 +1231,17 @@ impl Batch {
       }
     }
 
+    if self.fee_utxo_only && self.commitment.is_none() {
+      Self::check_fee_utxos_sufficient(
+        &commit_utxos,
+        reveal_fee + total_postage + extra_outputs_value,
+        self.commit_fee_rate.fee(TransactionBuilder::estimate_vbytes_with(
+          commit_utxos.len(),
+          vec![commit_tx_address.clone()],
+        )),
+      )?;
+    }
+
     let unsigned_commit_tx = if self.commitment.is_some() {
       Transaction {
         version: 0,
@@ -740,40 +1253,81 @@ impl Batch {
       TransactionBuilder::new(
       satpoints,
       wallet_inscriptions,
-      utxos.clone(),
+      commit_utxos,
       locked_utxos.clone(),
       runic_utxos,
       commit_tx_address.clone(),
       change,
       self.commit_fee_rate,
       if self.commit_only {
-        Target::NoChange(reveal_fee + total_postage)
+        Target::NoChange(reveal_fee + total_postage + extra_outputs_value)
       } else if !self.fee_utxos.is_empty() {
-        Target::ChangeIsFee(reveal_fee + total_postage)
+        Target::ChangeIsFee(reveal_fee + total_postage + extra_outputs_value)
       } else {
-        Target::Value(reveal_fee + total_postage)
+        Target::Value(reveal_fee + total_postage + extra_outputs_value)
       },
       force_input,
       self.no_wallet,
       )
-        .build_transaction()?
+        .build_transaction()
+        .map(|tx| Transaction {
+          version: self.commit_tx_version,
+          lock_time: LockTime::from_consensus(self.commit_locktime),
+          ..tx
+        })?
     };
 
+    if let (Mode::SameSat, Some(satpoint), None) = (self.mode, self.satpoint, self.commitment) {
+      if let Some(warning) = Self::check_sat_offset(
+        Self::commit_output_offset(&unsigned_commit_tx, &commit_tx_address, &utxos, satpoint)?,
+        self.postage,
+        self.destinations[0].script_pubkey().dust_value(),
+        self.preserve_sat_offset,
+      )? {
+        eprintln!("warning: {warning}");
+      }
+    }
+
+    if self.estimate {
+      let commit_fee = if self.commitment.is_some() {
+        0
+      } else {
+        Self::calculate_fee(&unsigned_commit_tx, &utxos)?
+      };
+
+      let reveal_fee = if self.commit_only { 0 } else { reveal_fee.to_sat() };
+
+      return Ok(BatchTransactions {
+        commit_address: if self.commitment.is_some() {
+          None
+        } else {
+          Some(commit_tx_address)
+        },
+        fee_estimate: Some(FeeEstimate {
+          commit_fee,
+          reveal_fee,
+          reveal_vsize,
+        }),
+        total_fees: Some(commit_fee + reveal_fee),
+        ..Default::default()
+      });
+    }
+
     let mut reveal_input_value = Amount::from_sat(0);
     let mut reveal_input_prevouts = Vec::new();
     for i in &self.reveal_input {
-      let output = index.get_transaction(i.txid)?.unwrap().output[i.vout as usize].clone();
+      let output = Self::get_reveal_input_output(index, *i)?;
       reveal_input_value += Amount::from_sat(output.value);
       reveal_input_prevouts.push(output.clone());
       utxos.insert(*i, Amount::from_sat(output.value));
     }
 
-    let vout = if self.commitment.is_some() {
-      reveal_inputs[commit_input] = self.commitment.unwrap();
+    let vout = if let Some(commitment) = self.commitment {
+      reveal_inputs[commit_input] = commitment;
 
       if self.reveal_fee != Some(Amount::from_sat(0)) {
         if let Some(last) = reveal_outputs.last_mut() {
-          (*last).value = (reveal_input_value + self.commitment_output.clone().unwrap().value - total_postage - reveal_fee).to_sat();
+          last.value = (reveal_input_value + self.commitment_output.clone().unwrap().value - total_postage - extra_outputs_value - reveal_fee).to_sat();
         }
       }
 
@@ -801,6 +1355,8 @@ impl Batch {
       commit_input,
       reveal_outputs.clone(),
       &reveal_script,
+      self.no_rbf,
+      self.reveal_tx_version,
     );
 
     if reveal_tx.output[commit_input].value
@@ -823,6 +1379,13 @@ impl Batch {
       }
     ];
 
+    Self::check_commit_output_margin(
+      &prevouts[0],
+      total_postage + extra_outputs_value,
+      reveal_fee,
+      self.commit_output_margin,
+    )?;
+
     if let Some(parent_info) = self.parent_info.clone() {
       prevouts.insert(0, parent_info.clone().tx_out);
       if self.no_wallet {
@@ -862,7 +1425,7 @@ impl Batch {
     );
 
     witness.push(reveal_script);
-    witness.push(&control_block.serialize());
+    witness.push(control_block.serialize());
 
     let recovery_key_pair = key_pair.tap_tweak(&secp256k1, taproot_spend_info.merkle_root());
 
@@ -883,6 +1446,19 @@ impl Batch {
       );
     }
 
+    let mut warnings = Vec::new();
+
+    if !self.no_limit
+      && reveal_weight.to_wu() * 100 > u64::from(MAX_STANDARD_TX_WEIGHT) * self.weight_warning_percent
+    {
+      let warning = format!(
+        "reveal transaction weight {reveal_weight} is above {}% of {MAX_STANDARD_TX_WEIGHT} (MAX_STANDARD_TX_WEIGHT); consider splitting the batch",
+        self.weight_warning_percent
+      );
+      eprintln!("warning: {warning}");
+      warnings.push(warning);
+    }
+
     utxos.insert(
       reveal_tx.input[commit_input].previous_output,
       if self.commitment.is_some() {
@@ -899,35 +1475,556 @@ impl Batch {
       if self.commitment.is_some() {
         0
       } else {
-        Self::calculate_fee(&unsigned_commit_tx, &utxos)
+        Self::calculate_fee(&unsigned_commit_tx, &utxos)?
       } + if self.commit_only {
         0
       } else {
-        Self::calculate_fee(&reveal_tx, &utxos)
+        Self::calculate_fee(&reveal_tx, &utxos)?
       };
 
-    Ok((Some(unsigned_commit_tx), Some(reveal_tx), Some(recovery_key_pair), Some(total_fees), None))
-  }
+    let commit_address = if self.commitment.is_some() {
+      None
+    } else {
+      Some(commit_tx_address)
+    };
 
-  fn get_recovery_key(
-    client: &Client,
-    recovery_key_pair: TweakedKeyPair,
-    network: Network,
-  ) -> Result<String> {
-    let recovery_private_key =
-      PrivateKey::new(recovery_key_pair.to_inner().secret_key(), network).to_wif();
-    Ok(format!(
-      "rawtr({})#{}",
-      recovery_private_key,
-      client
-        .get_descriptor_info(&format!("rawtr({})", recovery_private_key))?
-        .checksum
-    ))
+    Ok(BatchTransactions {
+      commit_address,
+      commit_tx: Some(unsigned_commit_tx),
+      handoff,
+      key: Some(PrivateKey::new(key_pair.secret_key(), chain.network()).to_wif()),
+      recovery_key_pair: Some(recovery_key_pair),
+      reveal_tx: Some(reveal_tx),
+      total_fees: Some(total_fees),
+      warnings,
+      ..Default::default()
+    })
   }
 
-  fn backup_recovery_key(
-    client: &Client,
-    recovery_key_pair: TweakedKeyPair,
+  // Addresses from the wallet are never reused unless `allow_reuse`. A
+  // requested address that is already used is a warning, or an error with
+  // `deny_reuse`.
+  pub(super) fn change_addresses(
+    requested: Vec<Option<Address>>,
+    allow_reuse: bool,
+    deny_reuse: bool,
+    warnings: &mut Vec<String>,
+    mut get_change_address: impl FnMut() -> Result<Address>,
+  ) -> Result<Vec<Address>> {
+    let mut addresses: Vec<Address> = Vec::new();
+
+    for requested in requested {
+      let address = match requested {
+        Some(address) => address,
+        None => {
+          let mut attempts = 0;
+          loop {
+            let address = get_change_address()?;
+
+            if allow_reuse || !addresses.contains(&address) {
+              break address;
+            }
+
+            attempts += 1;
+
+            if attempts == MAX_CHANGE_ADDRESS_ATTEMPTS {
+              bail!(
+                "wallet returned change address {address} again after {MAX_CHANGE_ADDRESS_ATTEMPTS} attempts; use --allow-change-reuse to allow this"
+              );
+            }
+          }
+        }
+      };
+
+      if !allow_reuse && addresses.contains(&address) {
+        if deny_reuse {
+          bail!("change address {address} is used more than once");
+        }
+
+        let warning = format!("change address {address} is used more than once");
+        eprintln!("warning: {warning}");
+        warnings.push(warning);
+      }
+
+      addresses.push(address);
+    }
+
+    Ok(addresses)
+  }
+
+  pub(super) fn write_json(path: &Path, value: &impl Serialize) -> Result {
+    let dir = match path.parent() {
+      Some(dir) if !dir.as_os_str().is_empty() => dir,
+      _ => Path::new("."),
+    };
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+      .with_context(|| format!("failed to create temporary file in `{}`", dir.display()))?;
+
+    serde_json::to_writer_pretty(&mut file, value)?;
+
+    file
+      .persist(path)
+      .with_context(|| format!("failed to write `{}`", path.display()))?;
+
+    Ok(())
+  }
+
+  // Inputs the commit transaction must spend: the fee utxos when they pay
+  // for the batch, otherwise any `--commit-input`s.
+  fn force_input(&self) -> Vec<OutPoint> {
+    if self.fee_utxos.is_empty() {
+      self.commit_input.clone()
+    } else {
+      self.fee_utxos.clone()
+    }
+  }
+
+  fn content_hashes(&self) -> Vec<String> {
+    self
+      .inscriptions
+      .iter()
+      .map(|inscription| sha256::Hash::hash(inscription.body().unwrap_or_default()).to_string())
+      .collect()
+  }
+
+  fn check_handoff(
+    &self,
+    path: &Path,
+    key_pair: &secp256k1::KeyPair,
+    commit_tx_address: &Address,
+  ) -> Result {
+    let handoff = serde_json::from_str::<super::Handoff>(
+      &fs::read_to_string(path)
+        .with_context(|| format!("failed to read handoff file `{}`", path.display()))?,
+    )
+    .with_context(|| format!("failed to parse handoff file `{}`", path.display()))?;
+
+    if PrivateKey::from_wif(&handoff.key)?.inner != key_pair.secret_key() {
+      bail!("--key doesn't match the key in handoff file `{}`", path.display());
+    }
+
+    let content_hashes = self.content_hashes();
+
+    if content_hashes.len() != handoff.content_hashes.len() {
+      bail!(
+        "batch has {} inscriptions but handoff file `{}` has {}",
+        content_hashes.len(),
+        path.display(),
+        handoff.content_hashes.len(),
+      );
+    }
+
+    if let Some(i) = content_hashes
+      .iter()
+      .zip(&handoff.content_hashes)
+      .position(|(content_hash, handoff_hash)| content_hash != handoff_hash)
+    {
+      bail!(
+        "content of inscription {i} doesn't match handoff file `{}`",
+        path.display()
+      );
+    }
+
+    if self.postage.to_sat() != handoff.postage {
+      bail!(
+        "postage {} doesn't match handoff postage {}",
+        self.postage,
+        Amount::from_sat(handoff.postage)
+      );
+    }
+
+    let handoff_address = handoff.commit_address.assume_checked();
+
+    if handoff_address.script_pubkey() != commit_tx_address.script_pubkey() {
+      bail!(
+        "commit address {commit_tx_address} doesn't match handoff commit address {handoff_address}"
+      );
+    }
+
+    if let (Some(commitment), Some(output)) = (self.commitment, &self.commitment_output) {
+      if output.script_pub_key.hex != handoff_address.script_pubkey().as_bytes() {
+        bail!("commitment {commitment} doesn't pay to handoff commit address {handoff_address}");
+      }
+    }
+
+    Ok(())
+  }
+
+  // Broadcasts with `send`, retrying transient errors. A retry after a send
+  // that reached the node but whose response was lost is rejected as already
+  // known, so those rejections count as `txid` having been sent. With
+  // `missing_inputs_transient`, missing inputs are also retried, for a reveal
+  // whose commit was only just sent and may not have reached the mempool yet.
+  pub(super) fn send_with_retries(
+    retries: u32,
+    backoff: Duration,
+    txid: Txid,
+    missing_inputs_transient: bool,
+    mut send: impl FnMut() -> Result<Txid, bitcoincore_rpc::Error>,
+  ) -> Result<Txid, bitcoincore_rpc::Error> {
+    let mut attempt = 0;
+
+    loop {
+      match send() {
+        Err(err) if Self::is_already_broadcast_error(&err) => return Ok(txid),
+        Err(err)
+          if attempt < retries
+            && (Self::is_transient_broadcast_error(&err)
+              || (missing_inputs_transient && Self::is_missing_inputs_error(&err))) =>
+        {
+          let delay = backoff * 2u32.pow(attempt);
+          eprintln!(
+            "Broadcast failed: {err}; retrying in {}ms ({} of {retries})",
+            delay.as_millis(),
+            attempt + 1,
+          );
+          thread::sleep(delay);
+          attempt += 1;
+        }
+        result => return result,
+      }
+    }
+  }
+
+  fn is_transient_broadcast_error(err: &bitcoincore_rpc::Error) -> bool {
+    use bitcoincore_rpc::jsonrpc::error::{Error, RpcError};
+
+    match err {
+      bitcoincore_rpc::Error::Io(_) | bitcoincore_rpc::Error::JsonRpc(Error::Transport(_)) => true,
+      // -28: node is warming up, -9: node is not connected to the network
+      bitcoincore_rpc::Error::JsonRpc(Error::Rpc(RpcError { code: -28 | -9, .. })) => true,
+      _ => false,
+    }
+  }
+
+  fn is_missing_inputs_error(err: &bitcoincore_rpc::Error) -> bool {
+    use bitcoincore_rpc::jsonrpc::error::{Error, RpcError};
+
+    match err {
+      // -25: transaction inputs are missing or already spent
+      bitcoincore_rpc::Error::JsonRpc(Error::Rpc(RpcError { code: -25, .. })) => true,
+      bitcoincore_rpc::Error::JsonRpc(Error::Rpc(RpcError { message, .. })) => {
+        message.contains("bad-txns-inputs-missingorspent")
+          || message.to_lowercase().contains("missing inputs")
+      }
+      _ => false,
+    }
+  }
+
+  fn is_already_broadcast_error(err: &bitcoincore_rpc::Error) -> bool {
+    use bitcoincore_rpc::jsonrpc::error::{Error, RpcError};
+
+    match err {
+      // -27: transaction already in block chain
+      bitcoincore_rpc::Error::JsonRpc(Error::Rpc(RpcError { code: -27, .. })) => true,
+      bitcoincore_rpc::Error::JsonRpc(Error::Rpc(RpcError { message, .. })) => {
+        message.contains("txn-already-known") || message.contains("txn-already-in-mempool")
+      }
+      _ => false,
+    }
+  }
+
+  pub(super) fn check_fee_utxos_sufficient(
+    commit_utxos: &BTreeMap<OutPoint, Amount>,
+    commit_value: Amount,
+    commit_fee: Amount,
+  ) -> Result {
+    let available = commit_utxos.values().cloned().sum::<Amount>();
+    let required = commit_value + commit_fee;
+
+    if available < required {
+      bail!(
+        "fee utxos insufficient: need {} more sats",
+        (required - available).to_sat()
+      );
+    }
+
+    Ok(())
+  }
+
+  pub(super) fn check_runic_inputs(
+    index: &Index,
+    runic_utxos: &BTreeSet<OutPoint>,
+    inputs: &[OutPoint],
+  ) -> Result {
+    let unclassified = inputs
+      .iter()
+      .filter(|outpoint| !runic_utxos.contains(outpoint))
+      .cloned()
+      .collect::<Vec<OutPoint>>();
+
+    let runic = index.get_runic_outputs(&unclassified)?;
+
+    for outpoint in inputs {
+      if runic_utxos.contains(outpoint) || runic.contains(outpoint) {
+        bail!("input {outpoint} contains runes; use --allow-spend-runic to spend it anyway");
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(super) fn check_commit_output_margin(
+    commit_output: &TxOut,
+    total_postage: Amount,
+    reveal_fee: Amount,
+    margin: Amount,
+  ) -> Result {
+    if margin == Amount::ZERO {
+      return Ok(());
+    }
+
+    let dust = commit_output.script_pubkey.dust_value();
+    let required = dust + total_postage + reveal_fee + margin;
+    let value = Amount::from_sat(commit_output.value);
+
+    if value < required {
+      bail!(
+        "commit output of {} sats does not cover dust ({} sats), postage ({} sats), reveal fee ({} sats) and margin ({} sats); {} sats required",
+        value.to_sat(),
+        dust.to_sat(),
+        total_postage.to_sat(),
+        reveal_fee.to_sat(),
+        margin.to_sat(),
+        required.to_sat(),
+      );
+    }
+
+    Ok(())
+  }
+
+  // Fills in `input` with a placeholder of the size that signing an output
+  // with `script_pubkey` would add, assuming the compressed keys and
+  // P2SH-wrapped P2WPKH that bitcoind wallets use.
+  pub(super) fn add_dummy_signature(input: &mut TxIn, script_pubkey: &Script) -> Result {
+    const ECDSA_SIGNATURE_SIZE: usize = 72;
+    const PUBLIC_KEY_SIZE: usize = 33;
+
+    if script_pubkey.is_v1_p2tr() {
+      input.witness = Witness::from_slice(&[&[0; SCHNORR_SIGNATURE_SIZE]]);
+    } else if script_pubkey.is_v0_p2wpkh() {
+      input.witness = Witness::from_slice(&[[0; ECDSA_SIGNATURE_SIZE].as_slice(), &[0; PUBLIC_KEY_SIZE]]);
+    } else if script_pubkey.is_p2sh() {
+      input.script_sig = ScriptBuf::builder().push_slice([0; 22]).into_script();
+      input.witness = Witness::from_slice(&[[0; ECDSA_SIGNATURE_SIZE].as_slice(), &[0; PUBLIC_KEY_SIZE]]);
+    } else if script_pubkey.is_p2pkh() {
+      input.script_sig = ScriptBuf::builder()
+        .push_slice([0; ECDSA_SIGNATURE_SIZE])
+        .push_slice([0; PUBLIC_KEY_SIZE])
+        .into_script();
+    } else {
+      bail!(
+        "can't estimate the signed size of input {} with script {script_pubkey}",
+        input.previous_output
+      );
+    }
+
+    Ok(())
+  }
+
+  pub(super) fn get_reveal_input_output(index: &Index, outpoint: OutPoint) -> Result<TxOut> {
+    index
+      .get_transaction(outpoint.txid)?
+      .ok_or_else(|| anyhow!("reveal input {outpoint} not found in index"))?
+      .output
+      .into_iter()
+      .nth(outpoint.vout.try_into().unwrap())
+      .ok_or_else(|| anyhow!("reveal input {outpoint} vout out of range"))
+  }
+
+  // The reveal spends the commit by txid, and its commit input signature uses
+  // SIGHASH_DEFAULT, which commits to the reveal's prevouts and so to that
+  // txid. Signing a segwit input doesn't change the txid, so the reveal stays
+  // valid once the commit is signed. Signing a non-segwit commit input would
+  // change the txid and invalidate the reveal, so those inputs are warned about.
+  fn commit_psbt(
+    index: &Index,
+    commit_tx: &Transaction,
+    warnings: &mut Vec<String>,
+  ) -> Result<Psbt> {
+    let mut psbt = Psbt::from_unsigned_tx(commit_tx.clone())?;
+
+    for (input, psbt_input) in commit_tx.input.iter().zip(psbt.inputs.iter_mut()) {
+      let outpoint = input.previous_output;
+
+      let output = index
+        .get_transaction(outpoint.txid)?
+        .ok_or_else(|| anyhow!("commit input {outpoint} not found in index"))?
+        .output
+        .into_iter()
+        .nth(outpoint.vout.try_into().unwrap())
+        .ok_or_else(|| anyhow!("commit input {outpoint} vout out of range"))?;
+
+      if !output.script_pubkey.is_witness_program() {
+        let warning = format!(
+          "commit input {outpoint} isn't segwit; signing it will change the commit txid and invalidate the reveal"
+        );
+        eprintln!("warning: {warning}");
+        warnings.push(warning);
+      }
+
+      psbt_input.witness_utxo = Some(output);
+    }
+
+    Ok(psbt)
+  }
+
+  fn reveal_psbt(
+    &self,
+    index: &Index,
+    commit_tx: &Transaction,
+    reveal_tx: &Transaction,
+  ) -> Result<Psbt> {
+    let mut unsigned_reveal_tx = reveal_tx.clone();
+    for input in &mut unsigned_reveal_tx.input {
+      input.witness = Witness::new();
+    }
+
+    let mut psbt = Psbt::from_unsigned_tx(unsigned_reveal_tx)?;
+
+    for (input, psbt_input) in reveal_tx.input.iter().zip(psbt.inputs.iter_mut()) {
+      let outpoint = input.previous_output;
+
+      psbt_input.witness_utxo = Some(if let Some(parent_info) = self
+        .parent_info
+        .as_ref()
+        .filter(|parent_info| parent_info.location.outpoint == outpoint)
+      {
+        psbt_input.tap_internal_key = self.reveal_parent_internal_key;
+        parent_info.tx_out.clone()
+      } else if Some(outpoint) == self.commitment {
+        let commitment_output = self.commitment_output.clone().unwrap();
+        TxOut {
+          value: commitment_output.value.to_sat(),
+          script_pubkey: commitment_output.script_pub_key.script()?,
+        }
+      } else if outpoint.txid == commit_tx.txid() {
+        commit_tx.output[outpoint.vout as usize].clone()
+      } else {
+        Self::get_reveal_input_output(index, outpoint)?
+      });
+
+      // the commit input is already signed with the reveal key
+      if !input.witness.is_empty() {
+        psbt_input.final_script_witness = Some(input.witness.clone());
+      }
+    }
+
+    Ok(psbt)
+  }
+
+  pub(super) fn finalize_reveal_psbt(mut psbt: Psbt) -> Result<Transaction> {
+    for (i, input) in psbt.inputs.iter_mut().enumerate() {
+      if input.final_script_witness.is_some() {
+        continue;
+      }
+
+      let Some(signature) = input.tap_key_sig else {
+        bail!("input {i} of reveal psbt isn't signed");
+      };
+
+      let mut witness = Witness::new();
+      witness.push(signature.to_vec());
+      input.final_script_witness = Some(witness);
+    }
+
+    Ok(psbt.extract_tx())
+  }
+
+  fn get_recovery_key(
+    client: &Client,
+    recovery_key_pair: TweakedKeyPair,
+    network: Network,
+  ) -> Result<String> {
+    let recovery_private_key =
+      PrivateKey::new(recovery_key_pair.to_inner().secret_key(), network).to_wif();
+    Ok(format!(
+      "rawtr({})#{}",
+      recovery_private_key,
+      client
+        .get_descriptor_info(&format!("rawtr({})", recovery_private_key))?
+        .checksum
+    ))
+  }
+
+  // The offset of the sat at `satpoint` within the commit output. The reveal
+  // spends the commit output into the inscription output after any parent, so
+  // this is also the sat's offset within the inscription output.
+  pub(super) fn commit_output_offset(
+    commit_tx: &Transaction,
+    commit_address: &Address,
+    utxos: &BTreeMap<OutPoint, Amount>,
+    satpoint: SatPoint,
+  ) -> Result<u64> {
+    let mut offset = 0;
+
+    for input in &commit_tx.input {
+      if input.previous_output == satpoint.outpoint {
+        offset += satpoint.offset;
+        break;
+      }
+
+      offset += utxos
+        .get(&input.previous_output)
+        .ok_or_else(|| anyhow!("wallet contains no cardinal utxo {}", input.previous_output))?
+        .to_sat();
+    }
+
+    let commit_script = commit_address.script_pubkey();
+
+    for output in &commit_tx.output {
+      if output.script_pubkey == commit_script {
+        return Ok(offset);
+      }
+
+      if offset < output.value {
+        break;
+      }
+
+      offset -= output.value;
+    }
+
+    Err(anyhow!("sat at {satpoint} isn't sent to the commit output"))
+  }
+
+  pub(super) fn check_sat_offset(
+    sat_offset: u64,
+    postage: Amount,
+    dust_value: Amount,
+    preserve_sat_offset: bool,
+  ) -> Result<Option<String>> {
+    if sat_offset >= postage.to_sat() {
+      return Err(anyhow!(
+        "sat offset {sat_offset} is outside of the {} sat inscription output; increase postage",
+        postage.to_sat()
+      ));
+    }
+
+    if postage.to_sat() - sat_offset > dust_value.to_sat() {
+      return Ok(None);
+    }
+
+    let message = format!(
+      "sat offset {sat_offset} is within {} sats of the end of the {} sat inscription output",
+      dust_value.to_sat(),
+      postage.to_sat()
+    );
+
+    if preserve_sat_offset {
+      Err(anyhow!("{message}; increase postage or drop --preserve-sat-offset"))
+    } else {
+      Ok(Some(message))
+    }
+  }
+
+  pub(super) fn recovery_key_fingerprint(recovery_key_pair: TweakedKeyPair) -> String {
+    hash160::Hash::hash(&recovery_key_pair.to_inner().x_only_public_key().0.serialize()).to_string()
+  }
+
+  fn backup_recovery_key(
+    client: &Client,
+    recovery_key_pair: TweakedKeyPair,
     network: Network,
   ) -> Result {
     let recovery_private_key = PrivateKey::new(recovery_key_pair.to_inner().secret_key(), network);
@@ -953,13 +2050,15 @@ impl Batch {
     Ok(())
   }
 
-  fn build_reveal_transaction(
+  pub(super) fn build_reveal_transaction(
     control_block: &ControlBlock,
     fee_rate: FeeRate,
     inputs: Vec<OutPoint>,
     commit_input_index: usize,
     outputs: Vec<TxOut>,
     script: &Script,
+    no_rbf: bool,
+    version: i32,
   ) -> (Transaction, Amount, u64) {
     let reveal_tx = Transaction {
       input: inputs
@@ -968,12 +2067,16 @@ impl Batch {
           previous_output: *outpoint,
           script_sig: script::Builder::new().into_script(),
           witness: Witness::new(),
-          sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+          sequence: if no_rbf {
+            Sequence::ENABLE_LOCKTIME_NO_RBF
+          } else {
+            Sequence::ENABLE_RBF_NO_LOCKTIME
+          },
         })
         .collect(),
       output: outputs,
       lock_time: LockTime::ZERO,
-      version: 2,
+      version,
     };
 
     let (fee, vsize) = {
@@ -988,7 +2091,7 @@ impl Batch {
               .to_vec(),
           );
           txin.witness.push(script);
-          txin.witness.push(&control_block.serialize());
+          txin.witness.push(control_block.serialize());
         } else {
           txin.witness = Witness::from_slice(&[&[0; SCHNORR_SIGNATURE_SIZE]]);
         }
@@ -1001,13 +2104,23 @@ impl Batch {
     (reveal_tx, fee, vsize)
   }
 
-  fn calculate_fee(tx: &Transaction, utxos: &BTreeMap<OutPoint, Amount>) -> u64 {
-    tx.input
+  pub(super) fn calculate_fee(tx: &Transaction, utxos: &BTreeMap<OutPoint, Amount>) -> Result<u64> {
+    let inputs = tx
+      .input
       .iter()
-      .map(|txin| utxos.get(&txin.previous_output).unwrap().to_sat())
-      .sum::<u64>()
-      .checked_sub(tx.output.iter().map(|txout| txout.value).sum::<u64>())
-      .unwrap()
+      .map(|txin| {
+        utxos
+          .get(&txin.previous_output)
+          .map(|amount| amount.to_sat())
+          .ok_or_else(|| anyhow!("value of transaction input {} is unknown", txin.previous_output))
+      })
+      .sum::<Result<u64>>()?;
+
+    let outputs = tx.output.iter().map(|txout| txout.value).sum::<u64>();
+
+    inputs
+      .checked_sub(outputs)
+      .ok_or_else(|| anyhow!("transaction outputs ({outputs}) exceed inputs ({inputs})"))
   }
 }
 
@@ -1025,9 +2138,11 @@ pub(crate) enum Mode {
 #[derive(Deserialize, Default, PartialEq, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct BatchEntry {
+  pub(crate) compress: Option<bool>,
+  pub(crate) content: Option<String>,
   pub(crate) delegate: Option<InscriptionId>,
   pub(crate) destination: Option<Address<NetworkUnchecked>>,
-  pub(crate) file: PathBuf,
+  pub(crate) file: Option<PathBuf>,
   pub(crate) metadata: Option<serde_yaml::Value>,
   pub(crate) metadata_json: Option<serde_json::Value>,
   pub(crate) metaprotocol: Option<String>,
@@ -1037,35 +2152,81 @@ pub(crate) struct BatchEntry {
 }
 
 impl BatchEntry {
-  pub(crate) fn metadata(&self) -> Result<Option<Vec<u8>>> {
-    Ok(match &self.metadata {
-      None => match &self.metadata_json {
+  pub(crate) fn metadata(&self, defaults: Option<&[u8]>) -> Result<Option<Vec<u8>>> {
+    let Some(defaults) = defaults else {
+      return Ok(match &self.metadata {
+        None => match &self.metadata_json {
+          Some(metadata) => {
+            let mut cbor = Vec::new();
+            ciborium::into_writer(&metadata, &mut cbor)?;
+            Some(cbor)
+          }
+          None => None,
+        }
         Some(metadata) => {
           let mut cbor = Vec::new();
           ciborium::into_writer(&metadata, &mut cbor)?;
           Some(cbor)
         }
-        None => None,
-      }
-      Some(metadata) => {
-        let mut cbor = Vec::new();
-        ciborium::into_writer(&metadata, &mut cbor)?;
-        Some(cbor)
+      });
+    };
+
+    let metadata = match (&self.metadata, &self.metadata_json) {
+      (Some(metadata), _) => Value::serialized(metadata)?,
+      (None, Some(metadata)) => Value::serialized(metadata)?,
+      (None, None) => return Ok(Some(defaults.to_vec())),
+    };
+
+    let defaults: Value = ciborium::from_reader(Cursor::new(defaults))
+      .context("failed to parse CBOR metadata")?;
+
+    let mut cbor = Vec::new();
+    ciborium::into_writer(&Self::merge_metadata(defaults, metadata)?, &mut cbor)?;
+    Ok(Some(cbor))
+  }
+
+  fn merge_metadata(defaults: Value, metadata: Value) -> Result<Value> {
+    let (Value::Map(mut merged), Value::Map(metadata)) = (defaults, metadata) else {
+      bail!("metadata can only be merged if both global and per-inscription metadata are maps");
+    };
+
+    for (key, value) in metadata {
+      match merged.iter_mut().find(|(existing, _)| *existing == key) {
+        Some((_, existing)) => {
+          *existing = if existing.is_map() && value.is_map() {
+            Self::merge_metadata(existing.clone(), value)?
+          } else {
+            value
+          };
+        }
+        None => merged.push((key, value)),
       }
-    })
+    }
+
+    Ok(Value::Map(merged))
   }
 }
 
+#[derive(Deserialize, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BatchOutput {
+  pub(crate) address: Address<NetworkUnchecked>,
+  pub(crate) value: u64,
+}
+
 #[derive(Deserialize, PartialEq, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct Batchfile {
   pub(crate) fees: Option<Vec<OutPoint>>,
   pub(crate) inscriptions: Vec<BatchEntry>,
   pub(crate) mode: Mode,
+  #[serde(default)]
+  pub(crate) outputs: Vec<BatchOutput>,
   pub(crate) parent: Option<InscriptionId>,
   pub(crate) parent_satpoint: Option<SatPoint>,
   pub(crate) postage: Option<u64>,
   pub(crate) sat: Option<Sat>,
+  pub(crate) sats: Option<Vec<Sat>>,
 }
 
 impl Batchfile {
@@ -1079,6 +2240,306 @@ impl Batchfile {
     Ok(batchfile)
   }
 
+  // Points each entry at the output holding its sat in `sats`, and returns
+  // those outputs. Unless `no_wallet`, each must be an unlocked wallet utxo.
+  pub(crate) fn assign_sats(
+    &mut self,
+    index: &Index,
+    utxos: &BTreeMap<OutPoint, Amount>,
+    locked_utxos: &BTreeSet<OutPoint>,
+    no_wallet: bool,
+  ) -> Result<Vec<OutPoint>> {
+    let Some(sats) = self.sats.clone() else {
+      return Ok(Vec::new());
+    };
+
+    if self.mode != Mode::SeparateOutputs {
+      bail!("`sats` can only be set in `separate-outputs` mode");
+    }
+
+    if sats.len() != self.inscriptions.len() {
+      bail!(
+        "`sats` contains {} sats but batch contains {} inscriptions",
+        sats.len(),
+        self.inscriptions.len()
+      );
+    }
+
+    if self.inscriptions.iter().any(|entry| entry.utxo.is_some()) {
+      bail!("`sats` and per-inscription `utxo` can't be used together");
+    }
+
+    if !index.has_sat_index() {
+      bail!("index must be built with `--index-sats` to use `sats`");
+    }
+
+    let mut outputs = BTreeMap::new();
+
+    for (entry, sat) in self.inscriptions.iter_mut().zip(sats) {
+      let Some(satpoint) = index.find(sat)? else {
+        bail!("could not find sat `{sat}`");
+      };
+
+      // each sat is inscribed on its own output, so it must be the first sat of that output
+      if satpoint.offset != 0 {
+        bail!(
+          "sat `{sat}` is at offset {} of output {}; it must be the first sat of its output to be used in `sats`",
+          satpoint.offset,
+          satpoint.outpoint,
+        );
+      }
+
+      if !no_wallet {
+        if !utxos.contains_key(&satpoint.outpoint) {
+          bail!(
+            "sat `{sat}` is in output {}, which is not an unspent output in the wallet",
+            satpoint.outpoint
+          );
+        }
+
+        if locked_utxos.contains(&satpoint.outpoint) {
+          bail!("sat `{sat}` is in output {}, which is locked", satpoint.outpoint);
+        }
+      }
+
+      if let Some(other) = outputs.insert(satpoint.outpoint, sat) {
+        bail!(
+          "sats `{other}` and `{sat}` are both in output {}",
+          satpoint.outpoint
+        );
+      }
+
+      entry.utxo = Some(satpoint.outpoint);
+    }
+
+    Ok(outputs.into_keys().collect())
+  }
+
+  pub(crate) fn outputs(&self, chain: Chain) -> Result<Vec<TxOut>> {
+    self
+      .outputs
+      .iter()
+      .map(|output| {
+        let address = output.address.clone().require_network(chain.network())?;
+        let script_pubkey = address.script_pubkey();
+        let dust = script_pubkey.dust_value().to_sat();
+
+        if output.value < dust {
+          bail!(
+            "output to {address} of {} sats is below the dust limit of {dust} sats",
+            output.value
+          );
+        }
+
+        Ok(TxOut {
+          script_pubkey,
+          value: output.value,
+        })
+      })
+      .collect()
+  }
+
+  // Returns `(duplicate, first)` index pairs for entries whose content was
+  // already seen earlier in the batch.
+  pub(crate) fn duplicates(&self) -> Result<Vec<(usize, usize)>> {
+    let mut hashes = BTreeMap::new();
+    let mut duplicates = Vec::new();
+
+    for (i, entry) in self.inscriptions.iter().enumerate() {
+      if entry.delegate.is_some() || entry.file.as_deref() == Some(Path::new("none")) {
+        continue;
+      }
+
+      let mut engine = sha256::Hash::engine();
+
+      match &entry.file {
+        Some(file) => {
+          io::copy(
+            &mut File::open(file).with_context(|| format!("io error reading {}", file.display()))?,
+            &mut engine,
+          )?;
+        }
+        None => match entry.content.as_deref() {
+          Some(content) if !content.is_empty() => {
+            io::copy(&mut content.as_bytes(), &mut engine)?;
+          }
+          _ => continue,
+        },
+      }
+
+      match hashes.entry(sha256::Hash::from_engine(engine)) {
+        btree_map::Entry::Vacant(vacant) => {
+          vacant.insert(i);
+        }
+        btree_map::Entry::Occupied(first) => duplicates.push((i, *first.get())),
+      }
+    }
+
+    Ok(duplicates)
+  }
+
+  // The reveal witness weight that would be saved if each duplicate were a
+  // delegate instead of repeating its content type, encoding and body.
+  //
+  // Duplicates can't actually be rewritten into delegates of their first
+  // occurrence: the reveal txid commits to every envelope through the commit
+  // output, so an envelope can't name an inscription in its own reveal.
+  pub(crate) fn duplicate_savings(
+    inscriptions: &[Inscription],
+    duplicates: &[(usize, usize)],
+  ) -> u64 {
+    duplicates
+      .iter()
+      .map(|(duplicate, first)| {
+        let inscription = &inscriptions[*duplicate];
+
+        let delegate = Inscription {
+          body: None,
+          content_encoding: None,
+          content_type: None,
+          delegate: Some(
+            InscriptionId {
+              txid: Txid::all_zeros(),
+              index: u32::try_from(*first).unwrap(),
+            }
+            .value(),
+          ),
+          ..inscription.clone()
+        };
+
+        let len = |inscription: &Inscription| {
+          inscription
+            .append_reveal_script_to_builder(ScriptBuf::builder())
+            .into_script()
+            .len() as u64
+        };
+
+        len(inscription).saturating_sub(len(&delegate))
+      })
+      .sum()
+  }
+
+  pub(crate) fn warn_duplicates(&self, inscriptions: &[Inscription]) -> Result {
+    let duplicates = self.duplicates()?;
+
+    if !duplicates.is_empty() {
+      eprintln!(
+        "warning: batch contains {} inscription(s) with duplicate content; inscribing them as delegates of an existing copy would save {} weight units",
+        duplicates.len(),
+        Self::duplicate_savings(inscriptions, &duplicates),
+      );
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn build_inscriptions(
+    &self,
+    chain: Chain,
+    parent_value: Option<u64>,
+    metadata: Option<&[u8]>,
+    postage: Amount,
+    compress: bool,
+    skip_pointer_for_none: bool,
+    allow_unbound: bool,
+    inscribe_on_specific_utxos: bool,
+    utxos: &BTreeMap<OutPoint, Amount>,
+  ) -> Result<Vec<Inscription>> {
+    let parent_value = parent_value.unwrap_or_default();
+
+    let output_values = self
+      .inscriptions
+      .iter()
+      .map(|entry| {
+        if inscribe_on_specific_utxos {
+          utxos[&entry.utxo.unwrap()].to_sat()
+        } else {
+          postage.to_sat()
+        }
+      })
+      .collect::<Vec<u64>>();
+
+    let mut pointer = parent_value;
+
+    let mut inscriptions = Vec::new();
+    for (i, entry) in self.inscriptions.iter().enumerate() {
+      if entry.offset.is_some() && entry.pointer.is_some() {
+        return Err(anyhow!("you can't specify `offset` and `pointer` for the same inscription (inscription {i})"));
+      }
+      let entry_pointer = match entry.pointer {
+        Some(pointer) => Some(pointer),
+        None => match entry.offset {
+          Some(offset) => Some(pointer + offset),
+          None => if i == 0 { None } else { Some(pointer) },
+        },
+      };
+
+      let explicit = entry.pointer.is_some() || entry.offset.is_some();
+
+      // same-sat auto pointers deliberately point past the output so they're ignored
+      let check = if explicit {
+        !allow_unbound
+      } else {
+        self.mode != Mode::SameSat
+      };
+
+      if let Some(entry_pointer) = entry_pointer.filter(|_| check) {
+        let (start, end) = match self.mode {
+          Mode::SeparateOutputs => (pointer, pointer + output_values[i]),
+          Mode::SharedOutput => (parent_value, parent_value + output_values.iter().sum::<u64>()),
+          Mode::SameSat => (parent_value, parent_value + output_values[0]),
+        };
+
+        if !(start..end).contains(&entry_pointer) {
+          return Err(anyhow!(
+            "pointer {entry_pointer} of inscription {i} is outside of its output, which spans sats {start} to {end}{}",
+            if explicit { "; use --allow-unbound to allow this" } else { "" }
+          ));
+        }
+      }
+
+      match &entry.file {
+        Some(file) => {
+          if entry.content.is_some() {
+            return Err(anyhow!("you can't specify `file` and `content` for the same inscription (inscription {i})"));
+          }
+
+          inscriptions.push(Inscription::from_file(
+            chain,
+            entry.delegate,
+            file,
+            self.parent,
+            entry_pointer,
+            entry.metaprotocol.clone(),
+            entry.metadata(metadata)?,
+            entry.compress.unwrap_or(compress),
+            skip_pointer_for_none,
+            entry.utxo,
+          )?);
+        }
+        None => {
+          let Some(metaprotocol) = entry.metaprotocol.clone() else {
+            return Err(anyhow!("inscription {i} has no `file`; inscriptions without a file must set `metaprotocol`"));
+          };
+
+          inscriptions.push(Inscription::from_content(
+            chain,
+            entry.content.clone().unwrap_or_default().into_bytes(),
+            self.parent,
+            entry_pointer,
+            metaprotocol,
+            entry.metadata(metadata)?,
+            entry.utxo,
+          )?);
+        }
+      }
+
+      pointer += output_values[i];
+    }
+
+    Ok(inscriptions)
+  }
+
   pub(crate) fn inscriptions(
     &self,
     client: &Client,
@@ -1088,6 +2549,7 @@ impl Batchfile {
     postage: Amount,
     compress: bool,
     skip_pointer_for_none: bool,
+    allow_unbound: bool,
     utxos: &mut BTreeMap<OutPoint, Amount>,
   ) -> Result<(Vec<Inscription>, Vec<Address>, bool, Vec<OutPoint>)> {
     assert!(!self.inscriptions.is_empty());
@@ -1123,54 +2585,23 @@ impl Batchfile {
       }
 
       for outpoint in self.inscriptions.iter().map(|entry| entry.utxo.unwrap()) {
-        if !utxos.contains_key(&outpoint) {
-          utxos.insert(outpoint, Amount::from_sat(client.get_raw_transaction(&outpoint.txid, None)?.output[outpoint.vout as usize].value));
+        if let btree_map::Entry::Vacant(entry) = utxos.entry(outpoint) {
+          entry.insert(Amount::from_sat(client.get_raw_transaction(&outpoint.txid, None)?.output[outpoint.vout as usize].value));
         }
       }
     }
 
-    if metadata.is_some() {
-      assert!(self
-        .inscriptions
-        .iter()
-        .all(|entry| entry.metadata.is_none()));
-    }
-
-    let mut pointer = parent_value.unwrap_or_default();
-
-    let mut inscriptions = Vec::new();
-    for (i, entry) in self.inscriptions.iter().enumerate() {
-      if entry.offset.is_some() && entry.pointer.is_some() {
-        return Err(anyhow!("you can't specify `offset` and `pointer` for the same inscription (inscription {i})"));
-      }
-      inscriptions.push(Inscription::from_file(
-        chain,
-        entry.delegate,
-        &entry.file,
-        self.parent,
-        match entry.pointer {
-          Some(pointer) => Some(pointer),
-          None => match entry.offset {
-            Some(offset) => Some(pointer + offset),
-            None => if i == 0 { None } else { Some(pointer) },
-          },
-        },
-        entry.metaprotocol.clone(),
-        match &metadata {
-          Some(metadata) => Some(metadata.clone()),
-          None => entry.metadata()?,
-        },
-        compress,
-        skip_pointer_for_none,
-        entry.utxo,
-      )?);
-
-      if inscribe_on_specific_utxos {
-        pointer += utxos[&entry.utxo.unwrap()].to_sat();
-      } else {
-        pointer += postage.to_sat();
-      }
-    }
+    let inscriptions = self.build_inscriptions(
+      chain,
+      parent_value,
+      metadata.as_deref(),
+      postage,
+      compress,
+      skip_pointer_for_none,
+      allow_unbound,
+      inscribe_on_specific_utxos,
+      utxos,
+    )?;
 
     let destinations = match self.mode {
       Mode::SharedOutput | Mode::SameSat => vec![get_change_address(client, chain)?],
@@ -1191,10 +2622,7 @@ impl Batchfile {
         .collect::<Result<Vec<_>, _>>()?,
     };
 
-    let fees = match self.fees.clone() {
-      Some(fees) => fees,
-      None => Vec::new(),
-    };
+    let fees = self.fees.clone().unwrap_or_default();
 
     Ok((inscriptions, destinations, inscribe_on_specific_utxos, fees))
   }
@@ -66,6 +66,13 @@ pub trait Api {
     sighash_type: Option<()>,
   ) -> Result<Value, jsonrpc_core::Error>;
 
+  #[rpc(name = "decoderawtransaction")]
+  fn decode_raw_transaction(
+    &self,
+    tx: String,
+    is_witness: Option<bool>,
+  ) -> Result<DecodeRawTransactionResult, jsonrpc_core::Error>;
+
   #[rpc(name = "sendrawtransaction")]
   fn send_raw_transaction(&self, tx: String) -> Result<String, jsonrpc_core::Error>;
 
@@ -17,9 +17,10 @@ use {
     Wtxid,
   },
   bitcoincore_rpc::json::{
-    Bip125Replaceable, CreateRawTransactionInput, Descriptor, EstimateMode, GetBalancesResult,
+    Bip125Replaceable, CreateRawTransactionInput, DecodeRawTransactionResult, Descriptor, EstimateMode, GetBalancesResult,
     GetBalancesResultEntry, GetBlockHeaderResult, GetBlockchainInfoResult, GetDescriptorInfoResult,
-    GetNetworkInfoResult, GetRawTransactionResult, GetTransactionResult,
+    GetNetworkInfoResult, GetRawTransactionResult, GetRawTransactionResultVout,
+    GetRawTransactionResultVoutScriptPubKey, GetTransactionResult,
     GetTransactionResultDetail, GetTransactionResultDetailCategory, GetWalletInfoResult,
     ImportDescriptors, ImportMultiResult, ListDescriptorsResult, ListTransactionResult,
     ListUnspentResultEntry, LoadWalletResult, SignRawTransactionInput, SignRawTransactionResult,
@@ -358,6 +358,28 @@ impl Api for Server {
     )
   }
 
+  fn decode_raw_transaction(
+    &self,
+    tx: String,
+    is_witness: Option<bool>,
+  ) -> Result<DecodeRawTransactionResult, jsonrpc_core::Error> {
+    assert_eq!(is_witness, None, "is_witness param not supported");
+
+    let tx: Transaction = deserialize(&hex::decode(tx).unwrap()).unwrap();
+
+    Ok(DecodeRawTransactionResult {
+      txid: tx.txid(),
+      hash: tx.wtxid(),
+      size: tx.size().try_into().unwrap(),
+      vsize: tx.vsize().try_into().unwrap(),
+      weight: tx.weight().to_wu().try_into().unwrap(),
+      version: tx.version.try_into().unwrap(),
+      locktime: tx.lock_time.to_consensus_u32(),
+      vin: Vec::new(),
+      vout: Vec::new(),
+    })
+  }
+
   fn send_raw_transaction(&self, tx: String) -> Result<String, jsonrpc_core::Error> {
     let tx: Transaction = deserialize(&hex::decode(tx).unwrap()).unwrap();
     self.state.lock().unwrap().mempool.push(tx.clone());
@@ -484,7 +506,7 @@ impl Api for Server {
     assert_eq!(blockhash, None, "Blockhash param is unsupported");
     if verbose.unwrap_or(false) {
       match self.state().transactions.get(&txid) {
-        Some(_) => Ok(
+        Some(tx) => Ok(
           serde_json::to_value(GetRawTransactionResult {
             in_active_chain: Some(true),
             hex: Vec::new(),
@@ -495,7 +517,23 @@ impl Api for Server {
             version: 2,
             locktime: 0,
             vin: Vec::new(),
-            vout: Vec::new(),
+            vout: tx
+              .output
+              .iter()
+              .enumerate()
+              .map(|(n, output)| GetRawTransactionResultVout {
+                value: Amount::from_sat(output.value),
+                n: n.try_into().unwrap(),
+                script_pub_key: GetRawTransactionResultVoutScriptPubKey {
+                  asm: output.script_pubkey.to_asm_string(),
+                  hex: output.script_pubkey.to_bytes(),
+                  req_sigs: None,
+                  type_: None,
+                  addresses: Vec::new(),
+                  address: None,
+                },
+              })
+              .collect(),
             blockhash: None,
             confirmations: Some(1),
             time: None,
@@ -506,7 +544,13 @@ impl Api for Server {
         None => Err(Self::not_found()),
       }
     } else {
-      match self.state().transactions.get(&txid) {
+      let state = self.state();
+
+      match state
+        .transactions
+        .get(&txid)
+        .or_else(|| state.mempool.iter().find(|tx| tx.txid() == txid))
+      {
         Some(tx) => Ok(Value::String(hex::encode(serialize(tx)))),
         None => Err(Self::not_found()),
       }
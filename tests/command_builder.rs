@@ -133,7 +133,7 @@ impl CommandBuilder {
   }
 
   #[track_caller]
-  fn run(self) -> (TempDir, String) {
+  fn run(self) -> (Arc<TempDir>, String) {
     let mut command = self.command();
     let child = command.spawn().unwrap();
 
@@ -158,7 +158,7 @@ impl CommandBuilder {
     self.expected_stderr.assert_match(stderr);
     self.expected_stdout.assert_match(stdout);
 
-    (Arc::try_unwrap(self.tempdir).unwrap(), stdout.into())
+    (self.tempdir, stdout.into())
   }
 
   pub(crate) fn run_and_extract_file(self, path: impl AsRef<Path>) -> String {
@@ -92,7 +92,7 @@ fn get_sat_with_inscription_on_common_sat_and_more_inscriptions() {
 
   rpc_server.mine_blocks(1);
   let inscription_id = InscriptionId {
-    txid: reveal,
+    txid: reveal.unwrap(),
     index: 0,
   };
 
@@ -118,7 +118,7 @@ fn get_sat_with_inscription_on_common_sat_and_more_inscriptions() {
       offset: 1,
       rarity: Rarity::Common,
       percentile: "0.000714285715119048%".into(),
-      satpoint: Some(SatPoint::from_str(&format!("{}:{}:{}", reveal, 0, 0)).unwrap()),
+      satpoint: Some(SatPoint::from_str(&format!("{}:{}:{}", reveal.unwrap(), 0, 0)).unwrap()),
       timestamp: 3,
       inscriptions: vec![inscription_id],
     }
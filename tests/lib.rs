@@ -17,7 +17,7 @@ use {
       block::BlockJson, inscription::InscriptionJson, inscriptions::InscriptionsJson,
       output::OutputJson, rune::RuneJson, runes::RunesJson, sat::SatJson, status::StatusHtml,
     },
-    Edict, InscriptionId, Rune, RuneEntry, RuneId, Runestone, SatPoint,
+    Edict, InscriptionId, Rune, RuneEntry, RuneId, Runestone, Sat, SatPoint,
   },
   pretty_assertions::assert_eq as pretty_assert_eq,
   regex::Regex,
@@ -121,7 +121,7 @@ fn inscribe(rpc_server: &test_bitcoincore_rpc::Handle) -> (InscriptionId, Txid)
 
   assert_eq!(output.inscriptions.len(), 1);
 
-  (output.inscriptions[0].id, output.reveal)
+  (output.inscriptions[0].id, output.reveal.unwrap())
 }
 
 mod command_builder;
@@ -129,7 +129,7 @@ fn multiple_inscriptions_appear_on_reveal_transaction_page() {
 
   let id0 = output.inscriptions[0].id;
   let id1 = output.inscriptions[1].id;
-  let reveal = output.reveal;
+  let reveal = output.reveal.unwrap();
 
   TestServer::spawn_with_args(&rpc_server, &[]).assert_response_regex(
     format!("/tx/{reveal}"),
@@ -1,5 +1,13 @@
 use {super::*, std::ops::Deref};
 
+fn wif_key(byte: u8) -> String {
+  bitcoin::PrivateKey::new(
+    bitcoin::secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap(),
+    bitcoin::Network::Bitcoin,
+  )
+  .to_wif()
+}
+
 #[test]
 fn inscribe_creates_inscriptions() {
   let rpc_server = test_bitcoincore_rpc::spawn();
@@ -391,254 +399,1414 @@ fn inscribe_with_dry_run_flag_fees_increase() {
 }
 
 #[test]
-fn inscribe_to_specific_destination() {
+fn inscribe_with_dry_run_flag_reports_commit_address() {
   let rpc_server = test_bitcoincore_rpc::spawn();
   create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  let destination = CommandBuilder::new("wallet receive")
-    .rpc_server(&rpc_server)
-    .run_and_deserialize_output::<ord::subcommand::wallet::receive::Output>()
-    .address;
+  let key = wif_key(1);
 
-  let txid = CommandBuilder::new(format!(
-    "wallet inscribe --destination {} --file degenerate.png --fee-rate 1",
-    destination.clone().assume_checked()
+  let commit_address = CommandBuilder::new(format!(
+    "wallet inscribe --dry-run --key {key} --file degenerate.png --fee-rate 1"
   ))
   .write("degenerate.png", [1; 520])
   .rpc_server(&rpc_server)
   .run_and_deserialize_output::<Inscribe>()
-  .reveal;
-
-  let reveal_tx = &rpc_server.mempool()[1]; // item 0 is the commit, item 1 is the reveal.
-  assert_eq!(reveal_tx.txid(), txid);
-  assert_eq!(
-    reveal_tx.output.first().unwrap().script_pubkey,
-    destination.payload.script_pubkey()
-  );
-}
+  .commit_address
+  .unwrap()
+  .parse::<Address<NetworkUnchecked>>()
+  .unwrap()
+  .assume_checked();
 
-#[test]
-fn inscribe_to_address_on_different_network() {
-  let rpc_server = test_bitcoincore_rpc::spawn();
-  create_wallet(&rpc_server);
-  rpc_server.mine_blocks(1);
+  assert!(rpc_server.mempool().is_empty());
 
-  CommandBuilder::new(
-    "wallet inscribe --destination tb1qsgx55dp6gn53tsmyjjv4c2ye403hgxynxs0dnm --file degenerate.png --fee-rate 1"
-  )
+  CommandBuilder::new(format!(
+    "wallet inscribe --key {key} --file degenerate.png --fee-rate 1"
+  ))
   .write("degenerate.png", [1; 520])
   .rpc_server(&rpc_server)
-  .expected_exit_code(1)
-  .stderr_regex("error: address tb1qsgx55dp6gn53tsmyjjv4c2ye403hgxynxs0dnm belongs to network testnet which is different from required bitcoin\n")
-  .run_and_extract_stdout();
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert!(rpc_server.mempool()[0]
+    .output
+    .iter()
+    .any(|output| output.script_pubkey == commit_address.script_pubkey()));
 }
 
 #[test]
-fn inscribe_with_no_limit() {
+fn inscribe_with_commitment_sends_reveal_change_to_reveal_change_address() {
   let rpc_server = test_bitcoincore_rpc::spawn();
   create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  let four_megger = std::iter::repeat(0).take(4_000_000).collect::<Vec<u8>>();
-  CommandBuilder::new("wallet inscribe --no-limit degenerate.png --fee-rate 1")
-    .write("degenerate.png", four_megger)
-    .rpc_server(&rpc_server);
-}
+  let key = wif_key(1);
 
-#[test]
-fn inscribe_works_with_postage() {
-  let rpc_server = test_bitcoincore_rpc::spawn();
-  create_wallet(&rpc_server);
-  rpc_server.mine_blocks(1);
+  let output = CommandBuilder::new(format!(
+    "wallet inscribe --commit-only --key {key} --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
 
-  CommandBuilder::new("wallet inscribe --file foo.txt --postage 5btc --fee-rate 10".to_string())
-    .write("foo.txt", [0; 350])
-    .rpc_server(&rpc_server)
-    .run_and_deserialize_output::<Inscribe>();
+  let commit_address = output
+    .commit_address
+    .unwrap()
+    .parse::<Address<NetworkUnchecked>>()
+    .unwrap()
+    .assume_checked();
+
+  let vout = rpc_server.mempool()[0]
+    .output
+    .iter()
+    .position(|output| output.script_pubkey == commit_address.script_pubkey())
+    .unwrap();
+
+  let commitment = OutPoint {
+    txid: output.commit.unwrap(),
+    vout: vout.try_into().unwrap(),
+  };
 
   rpc_server.mine_blocks(1);
 
-  let inscriptions = CommandBuilder::new("wallet inscriptions".to_string())
-    .write("foo.txt", [0; 350])
-    .rpc_server(&rpc_server)
-    .run_and_deserialize_output::<Vec<ord::subcommand::wallet::inscriptions::Output>>();
+  let reveal_change = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    .parse::<Address<NetworkUnchecked>>()
+    .unwrap()
+    .assume_checked();
 
-  pretty_assert_eq!(inscriptions[0].postage, 5 * COIN_VALUE);
+  CommandBuilder::new(format!(
+    "wallet inscribe --commitment {commitment} --key {key} --file foo.txt --fee-rate 1 --reveal-change {reveal_change}"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(
+    rpc_server.mempool()[0].output.last().unwrap().script_pubkey,
+    reveal_change.script_pubkey()
+  );
 }
 
 #[test]
-fn inscribe_with_non_existent_parent_inscription() {
+fn inscribe_with_dump_json_writes_recovery_material() {
   let rpc_server = test_bitcoincore_rpc::spawn();
   create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  let parent_id = "0000000000000000000000000000000000000000000000000000000000000000i0";
+  let key = wif_key(1);
 
-  CommandBuilder::new(format!(
-    "wallet inscribe --fee-rate 1.0 --parent {parent_id} --file child.png"
+  let dump = CommandBuilder::new(format!(
+    "wallet inscribe --no-broadcast --key {key} --dump-json dump.json --file foo.txt --fee-rate 1"
   ))
-  .write("child.png", [1; 520])
+  .write("foo.txt", "FOO")
   .rpc_server(&rpc_server)
-  .expected_stderr(format!("error: parent {parent_id} does not exist\n"))
-  .expected_exit_code(1)
-  .run_and_extract_stdout();
+  .stdout_regex(".*")
+  .run_and_extract_file("dump.json");
+
+  assert!(rpc_server.mempool().is_empty());
+
+  let dump = serde_json::from_str::<ord::subcommand::wallet::inscribe::RecoveryDump>(&dump).unwrap();
+
+  assert!(dump.commit_address.is_some());
+  assert!(dump.commit_hex.is_some());
+  assert!(dump.reveal_hex.is_some());
+  assert_eq!(dump.inscriptions.len(), 1);
+  assert_eq!(dump.key, key);
+  assert!(dump.recovery_descriptor.starts_with("rawtr("));
 }
 
 #[test]
-fn inscribe_with_parent_inscription_and_fee_rate() {
+fn dump_json_records_generated_key() {
+  use bitcoin::{
+    consensus::encode::deserialize,
+    hashes::hex::FromHex,
+    secp256k1::{KeyPair, Secp256k1, XOnlyPublicKey},
+    PrivateKey, Transaction,
+  };
+
   let rpc_server = test_bitcoincore_rpc::spawn();
   create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  let parent_output = CommandBuilder::new("wallet inscribe --fee-rate 5.0 --file parent.png")
-    .write("parent.png", [1; 520])
-    .rpc_server(&rpc_server)
-    .run_and_deserialize_output::<Inscribe>();
+  let dump = CommandBuilder::new(
+    "wallet inscribe --no-broadcast --dump-json dump.json --file foo.txt --fee-rate 1",
+  )
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .stdout_regex(".*")
+  .run_and_extract_file("dump.json");
 
-  assert_eq!(rpc_server.descriptors().len(), 3);
-  let parent_id = parent_output.inscriptions[0].id;
+  let dump = serde_json::from_str::<ord::subcommand::wallet::inscribe::RecoveryDump>(&dump).unwrap();
 
-  let commit_tx = &rpc_server.mempool()[0];
-  let reveal_tx = &rpc_server.mempool()[1];
+  let (public_key, _parity) = XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(
+    &Secp256k1::new(),
+    &PrivateKey::from_wif(&dump.key).unwrap().inner,
+  ));
+
+  let reveal =
+    deserialize::<Transaction>(&Vec::<u8>::from_hex(&dump.reveal_hex.unwrap()).unwrap()).unwrap();
 
+  // the reveal script starts by pushing the key that the dump records
   assert_eq!(
-    ord::FeeRate::try_from(5.0)
-      .unwrap()
-      .fee(commit_tx.vsize() + reveal_tx.vsize())
-      .to_sat(),
-    parent_output.total_fees
+    reveal.input[0].witness.to_vec()[1][1..33],
+    public_key.serialize(),
   );
+}
 
-  rpc_server.mine_blocks(1);
+#[test]
+fn dump_json_conflicts_with_paths_that_do_not_write_it() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
 
-  let child_output = CommandBuilder::new(format!(
-    "wallet inscribe --fee-rate 7.3 --parent {parent_id} --file child.png"
+  for flag in ["--dry-run", "--estimate", "--no-wallet", "--psbt-package", "--reveal-parent-psbt"] {
+    CommandBuilder::new(format!(
+      "wallet inscribe --dump-json dump.json {flag} --file foo.txt --fee-rate 1"
+    ))
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .stderr_regex(".*the argument '--dump-json <DUMP_JSON>' cannot be used with.*")
+    .expected_exit_code(2)
+    .run_and_extract_stdout();
+  }
+}
+
+#[test]
+fn inscribe_with_unconfirmed_parent_and_parent_satpoint() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let parent_txid = rpc_server.mine_blocks(1)[0].txdata[0].txid();
+  let child_txid = rpc_server.mine_blocks(1)[0].txdata[0].txid();
+
+  let parent_output = CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 1 --satpoint {parent_txid}:0:0 --file parent.png"
   ))
-  .write("child.png", [1; 520])
+  .write("parent.png", [1; 520])
   .rpc_server(&rpc_server)
   .run_and_deserialize_output::<Inscribe>();
 
-  assert_eq!(rpc_server.descriptors().len(), 4);
-  assert_eq!(parent_id, child_output.parent.unwrap());
+  let parent_id = parent_output.inscriptions[0].id;
+  let parent_location = parent_output.inscriptions[0].location;
 
-  let commit_tx = &rpc_server.mempool()[0];
-  let reveal_tx = &rpc_server.mempool()[1];
+  let child_output = CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 1 --satpoint {child_txid}:0:0 --parent {parent_id} --parent-satpoint {parent_location} --utxo {} --file child.png",
+    parent_location.outpoint,
+  ))
+  .write("child.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
 
-  assert_eq!(
-    ord::FeeRate::try_from(7.3)
-      .unwrap()
-      .fee(commit_tx.vsize() + reveal_tx.vsize())
-      .to_sat(),
-    child_output.total_fees
-  );
+  assert_eq!(child_output.parent, Some(parent_id));
+}
 
+#[test]
+fn inscribe_with_sat_range_uses_lowest_wallet_sat_in_range() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
+  let txid = rpc_server.mine_blocks(1)[0].txdata[0].txid();
 
-  let ord_server = TestServer::spawn_with_args(&rpc_server, &[]);
-
-  ord_server.assert_response_regex(
-    format!("/inscription/{}", child_output.parent.unwrap()),
-    format!(
-      ".*<dt>children</dt>.*<a href=/inscription/{}>.*",
-      child_output.inscriptions[0].id
-    ),
-  );
+  CommandBuilder::new(
+    "--index-sats wallet inscribe --sat-range 10000000010-10000000020 --file foo.txt --fee-rate 1",
+  )
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
 
-  ord_server.assert_response_regex(
-    format!("/inscription/{}", child_output.inscriptions[0].id),
-    format!(
-      ".*<dt>parent</dt>.*<a class=monospace href=/inscription/{}>.*",
-      child_output.parent.unwrap()
-    ),
-  );
+  assert!(rpc_server.mempool()[0]
+    .input
+    .iter()
+    .any(|input| input.previous_output == OutPoint { txid, vout: 0 }));
 }
 
 #[test]
-fn reinscribe_with_flag() {
+fn inscribe_with_sat_range_outside_of_wallet_fails() {
   let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  assert_eq!(rpc_server.descriptors().len(), 0);
+  CommandBuilder::new("--index-sats wallet inscribe --sat-range 0-10 --file foo.txt --fee-rate 1")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .expected_exit_code(1)
+    .expected_stderr("error: wallet contains no sat in range `0-10`\n")
+    .run_and_extract_stdout();
+}
 
+#[test]
+fn batch_same_sat_reports_inscribed_sat_with_sat_index() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
   create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
 
-  let inscribe = CommandBuilder::new("wallet inscribe --file tulip.png --fee-rate 5.0 ")
-    .write("tulip.png", [1; 520])
+  let output = CommandBuilder::new("--index-sats wallet inscribe --fee-rate 1 --batch batch.yaml")
+    .write("inscription.txt", "Hello World")
+    .write("tulip.png", [0; 555])
+    .write(
+      "batch.yaml",
+      "mode: same-sat\nsat: 5000000000\ninscriptions:\n- file: inscription.txt\n- file: tulip.png\n",
+    )
     .rpc_server(&rpc_server)
     .run_and_deserialize_output::<Inscribe>();
 
-  assert_eq!(rpc_server.descriptors().len(), 3);
-
-  let txid = rpc_server.mine_blocks(1)[0].txdata[2].txid();
-
-  let ord_server = TestServer::spawn_with_args(&rpc_server, &[]);
-  let request = ord_server.request(format!("/content/{}", inscribe.inscriptions[0].id));
-
-  assert_eq!(request.status(), 200);
-
-  let reinscribe = CommandBuilder::new(format!(
-    "wallet inscribe --file orchid.png --fee-rate 1.1 --reinscribe --satpoint {txid}:0:0"
-  ))
-  .write("orchid.png", [1; 520])
-  .rpc_server(&rpc_server)
-  .run_and_deserialize_output::<Inscribe>();
+  assert_eq!(output.inscriptions[0].sat, Some(Sat(5_000_000_000)));
+  assert_eq!(output.inscriptions[1].sat, Some(Sat(5_000_000_000)));
+}
 
+#[test]
+fn inscribe_without_sat_index_does_not_report_sat() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  let ord_server = TestServer::spawn_with_args(&rpc_server, &["--index-sats"]);
-  let request = ord_server.request(format!("/content/{}", reinscribe.inscriptions[0].id));
+  let output = CommandBuilder::new("wallet inscribe --fee-rate 1 --file foo.txt")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
 
-  assert_eq!(request.status(), 200);
-  ord_server.assert_response_regex(
-    format!("/sat/{}", 50 * COIN_VALUE),
-    format!(
-      ".*<dt>inscriptions</dt>.*<a href=/inscription/{}>.*<a href=/inscription/{}>.*",
-      inscribe.inscriptions[0].id, reinscribe.inscriptions[0].id
-    ),
-  );
+  assert_eq!(output.inscriptions[0].sat, None);
 }
 
 #[test]
-fn with_reinscribe_flag_but_not_actually_a_reinscription() {
+fn batch_extra_outputs_are_added_to_reveal_transaction() {
   let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
   rpc_server.mine_blocks(1);
 
-  assert_eq!(rpc_server.descriptors().len(), 0);
-
-  create_wallet(&rpc_server);
+  let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    .parse::<Address<NetworkUnchecked>>()
+    .unwrap()
+    .assume_checked();
 
-  CommandBuilder::new("wallet inscribe --file tulip.png --fee-rate 5.0 ")
-    .write("tulip.png", [1; 520])
+  let output = CommandBuilder::new("wallet inscribe --fee-rate 1 --batch batch.yaml")
+    .write("inscription.txt", "Hello World")
+    .write(
+      "batch.yaml",
+      format!("mode: separate-outputs\ninscriptions:\n- file: inscription.txt\noutputs:\n- address: {address}\n  value: 1234\n"),
+    )
     .rpc_server(&rpc_server)
     .run_and_deserialize_output::<Inscribe>();
 
-  let coinbase = rpc_server.mine_blocks(1)[0].txdata[0].txid();
+  let commit_tx = &rpc_server.mempool()[0];
+  let reveal_tx = &rpc_server.mempool()[1];
 
-  CommandBuilder::new(format!(
-    "wallet inscribe --file orchid.png --fee-rate 1.1 --reinscribe --satpoint {coinbase}:0:0"
-  ))
-  .write("orchid.png", [1; 520])
-  .rpc_server(&rpc_server)
-  .expected_exit_code(1)
-  .stderr_regex("error: reinscribe flag set but this would not be a reinscription.*")
-  .run_and_extract_stdout();
+  assert_eq!(reveal_tx.output.len(), 2);
+  assert_eq!(reveal_tx.output[1].script_pubkey, address.script_pubkey());
+  assert_eq!(reveal_tx.output[1].value, 1234);
+  assert_eq!(output.inscriptions[0].location.outpoint.vout, 0);
+
+  let commit_fee = commit_tx
+    .input
+    .iter()
+    .map(|input| {
+      rpc_server
+        .get_utxo_amount(&input.previous_output)
+        .unwrap()
+        .to_sat()
+    })
+    .sum::<u64>()
+    - commit_tx.output.iter().map(|output| output.value).sum::<u64>();
+
+  let reveal_fee = reveal_tx
+    .input
+    .iter()
+    .map(|input| commit_tx.output[input.previous_output.vout as usize].value)
+    .sum::<u64>()
+    - reveal_tx.output.iter().map(|output| output.value).sum::<u64>();
+
+  assert_eq!(output.total_fees, commit_fee + reveal_fee);
 }
 
 #[test]
-fn try_reinscribe_without_flag() {
+fn batch_extra_outputs_below_dust_are_rejected() {
   let rpc_server = test_bitcoincore_rpc::spawn();
-  rpc_server.mine_blocks(1);
-
-  assert_eq!(rpc_server.descriptors().len(), 0);
-
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new("wallet inscribe --fee-rate 1 --batch batch.yaml")
+    .write("inscription.txt", "Hello World")
+    .write(
+      "batch.yaml",
+      "mode: separate-outputs\ninscriptions:\n- file: inscription.txt\noutputs:\n- address: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4\n  value: 1\n",
+    )
+    .rpc_server(&rpc_server)
+    .expected_exit_code(1)
+    .stderr_regex("error: output to .* of 1 sats is below the dust limit of .* sats\n")
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn dry_run_with_skip_index_update_uses_existing_index() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let tempdir = Arc::new(TempDir::new().unwrap());
+
+  CommandBuilder::new("wallet inscribe --dry-run --fee-rate 1 --file foo.txt")
+    .temp_dir(tempdir.clone())
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  let (parent_id, _reveal) = inscribe(&rpc_server);
+
+  let height = index_height(&rpc_server, &tempdir);
+
+  assert!(height < rpc_server.height());
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --dry-run --skip-index-update --fee-rate 1 --file foo.txt --parent {parent_id}"
+  ))
+  .temp_dir(tempdir.clone())
+  .rpc_server(&rpc_server)
+  .stderr_regex(format!(
+    ".*warning: parent {parent_id} not found in index; fees are estimated without the parent input.*"
+  ))
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(index_height(&rpc_server, &tempdir), height);
+
+  // the parent was only missing because the index wasn't updated
+  let output = CommandBuilder::new(format!(
+    "wallet inscribe --dry-run --fee-rate 1 --file foo.txt --parent {parent_id}"
+  ))
+  .temp_dir(tempdir.clone())
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(output.parent, Some(parent_id));
+
+  assert_eq!(index_height(&rpc_server, &tempdir), rpc_server.height());
+}
+
+// Reads the height of the index in `tempdir` from a server that doesn't
+// update it.
+fn index_height(rpc_server: &test_bitcoincore_rpc::Handle, tempdir: &Arc<TempDir>) -> u64 {
+  let port = TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+
+  let mut child = CommandBuilder::new(format!(
+    "server --no-sync --address 127.0.0.1 --http-port {port}"
+  ))
+  .rpc_server(rpc_server)
+  .temp_dir(tempdir.clone())
+  .command()
+  .spawn()
+  .unwrap();
+
+  let mut attempt = 0;
+
+  let height = loop {
+    if let Ok(response) = reqwest::blocking::get(format!("http://localhost:{port}/blockheight")) {
+      if response.status() == 200 {
+        break response.text().unwrap().parse().unwrap();
+      }
+    }
+
+    if attempt == 100 {
+      panic!("Server did not respond to status check");
+    }
+
+    attempt += 1;
+
+    thread::sleep(Duration::from_millis(50));
+  };
+
+  // interrupt rather than kill, so that the index is closed cleanly
+  assert!(Command::new("kill")
+    .args(["-INT", &child.id().to_string()])
+    .status()
+    .unwrap()
+    .success());
+
+  child.wait().unwrap();
+
+  height
+}
+
+#[test]
+fn dry_run_with_missing_parent_is_an_error() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let parent_id = "0000000000000000000000000000000000000000000000000000000000000000i0";
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --dry-run --fee-rate 1 --file foo.txt --parent {parent_id}"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .expected_stderr(format!("error: parent {parent_id} does not exist\n"))
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn skip_index_update_requires_dry_run() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("wallet inscribe --skip-index-update --fee-rate 1 --file foo.txt")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .stderr_regex(".*the following required arguments were not provided.*--dry-run.*")
+    .expected_exit_code(2)
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_metaprotocol_only_content() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new([
+    "wallet",
+    "inscribe",
+    "--fee-rate",
+    "1",
+    "--metaprotocol",
+    "brc-20",
+    "--content",
+    "",
+  ])
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  rpc_server.mine_blocks(1);
+
+  let inscription = output.inscriptions[0].id;
+
+  let request =
+    TestServer::spawn_with_args(&rpc_server, &[]).request(format!("/content/{inscription}"));
+
+  assert_eq!(request.status(), 200);
+  assert_eq!(request.text().unwrap(), "");
+}
+
+#[test]
+fn content_requires_metaprotocol() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("wallet inscribe --fee-rate 1 --content foo")
+    .rpc_server(&rpc_server)
+    .stderr_regex(".*the following required arguments were not provided.*--metaprotocol.*")
+    .expected_exit_code(2)
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_reports_commit_inputs() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  let txid = rpc_server.mine_blocks(1)[0].txdata[0].txid();
+
+  let dry_run = CommandBuilder::new("wallet inscribe --dry-run --file foo.txt --fee-rate 1")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(dry_run.commit_inputs, vec![OutPoint { txid, vout: 0 }]);
+
+  let output = CommandBuilder::new("wallet inscribe --file foo.txt --fee-rate 1")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  let commit = &rpc_server.mempool()[0];
+
+  assert_eq!(commit.txid(), output.commit.unwrap());
+  assert_eq!(
+    output.commit_inputs,
+    commit
+      .input
+      .iter()
+      .map(|input| input.previous_output)
+      .collect::<Vec<OutPoint>>(),
+  );
+  assert_eq!(output.commit_inputs, vec![OutPoint { txid, vout: 0 }]);
+}
+
+#[test]
+fn inscribe_reports_content_hashes() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new("wallet inscribe --file foo.txt --fee-rate 1")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(
+    output.content_hashes,
+    vec!["9520437ce8902eb379a7d8aaa98fc4c94eeb07b6684854868fa6f72bf34b0fd3".to_string()],
+  );
+
+  rpc_server.mine_blocks(1);
+
+  let delegate = output.inscriptions[0].id;
+
+  let output = CommandBuilder::new("wallet inscribe --batch batch.yaml --fee-rate 1")
+    .write("foo.txt", "FOO")
+    .write(
+      "batch.yaml",
+      format!("mode: separate-outputs\ninscriptions:\n- delegate: {delegate}\n  file: foo.txt\n"),
+    )
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(output.content_hashes, vec![delegate.to_string()]);
+}
+
+#[test]
+fn inscribe_with_custom_tx_versions_and_commit_locktime() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(
+    "wallet inscribe --file foo.txt --fee-rate 1 --commit-tx-version 1 --reveal-tx-version 1 --commit-locktime 100",
+  )
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  let commit = &rpc_server.mempool()[0];
+  let reveal = &rpc_server.mempool()[1];
+
+  assert_eq!(commit.version, 1);
+  assert_eq!(commit.lock_time, bitcoin::absolute::LockTime::from_consensus(100));
+  assert_eq!(reveal.version, 1);
+  assert_eq!(reveal.lock_time, bitcoin::absolute::LockTime::ZERO);
+}
+
+#[test]
+fn inscribe_rejects_invalid_tx_version() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new("wallet inscribe --file foo.txt --fee-rate 1 --commit-tx-version 3")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: --commit-tx-version must be 1 or 2\n")
+    .expected_exit_code(1)
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn preview_envelope_prints_reveal_script_without_wallet() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  let output = CommandBuilder::new(
+    "wallet inscribe --file foo.txt --metaprotocol brc-20 --fee-rate 1 --preview-envelope",
+  )
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<ord::subcommand::wallet::inscribe::EnvelopePreview>();
+
+  assert_eq!(output.inscriptions.len(), 1);
+  assert_eq!(
+    output.inscriptions[0].content_type.as_deref(),
+    Some("text/plain;charset=utf-8")
+  );
+  assert_eq!(output.inscriptions[0].metaprotocol.as_deref(), Some("brc-20"));
+  assert_eq!(output.inscriptions[0].body_length, Some(3));
+  assert!(output.reveal_script.contains(&hex::encode("brc-20")));
+  assert!(rpc_server.mempool().is_empty());
+}
+
+#[test]
+fn json_metadata_from_stdin_matches_file() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  let from_stdin = CommandBuilder::new(
+    "wallet inscribe --file foo.txt --json-metadata - --fee-rate 1 --preview-envelope",
+  )
+  .write("foo.txt", "FOO")
+  .stdin(br#"{"foo":"bar"}"#.to_vec())
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<ord::subcommand::wallet::inscribe::EnvelopePreview>();
+
+  let from_file = CommandBuilder::new(
+    "wallet inscribe --file foo.txt --json-metadata metadata.json --fee-rate 1 --preview-envelope",
+  )
+  .write("foo.txt", "FOO")
+  .write("metadata.json", r#"{"foo":"bar"}"#)
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<ord::subcommand::wallet::inscribe::EnvelopePreview>();
+
+  assert!(from_stdin.inscriptions[0].metadata_length.is_some());
+  assert_eq!(from_stdin, from_file);
+}
+
+#[test]
+fn inscribe_with_commitment_below_min_commit_confirmations_fails() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let key = wif_key(1);
+
+  let output = CommandBuilder::new(format!(
+    "wallet inscribe --commit-only --key {key} --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  let commit = output.commit.unwrap();
+
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commitment {commit}:0 --key {key} --file foo.txt --fee-rate 1 --min-commit-confirmations 2"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .expected_exit_code(1)
+  .expected_stderr(format!(
+    "error: commitment {commit} has 1 confirmations but --min-commit-confirmations requires 2\n"
+  ))
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn min_commit_confirmations_requires_commitment() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  CommandBuilder::new("wallet inscribe --file foo.txt --fee-rate 1 --min-commit-confirmations 1")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .expected_exit_code(1)
+    .expected_stderr("error: --min-commit-confirmations only works with --commitment\n")
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_reports_recovery_key_fingerprint_without_dump() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let key = wif_key(1);
+
+  let first = CommandBuilder::new(format!(
+    "wallet inscribe --key {key} --file foo.txt --fee-rate 1 --dry-run"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  let second = CommandBuilder::new(format!(
+    "wallet inscribe --key {key} --file foo.txt --fee-rate 1 --dry-run"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert!(first.recovery_descriptor.is_none());
+  assert_eq!(first.recovery_key_fingerprint.as_ref().unwrap().len(), 40);
+  assert_eq!(first.recovery_key_fingerprint, second.recovery_key_fingerprint);
+}
+
+#[test]
+fn batch_same_sat_with_preserve_sat_offset_rejects_dust_postage() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(2);
+
+  CommandBuilder::new("--index-sats wallet inscribe --fee-rate 1 --batch batch.yaml --preserve-sat-offset")
+    .write("inscription.txt", "Hello World")
+    .write(
+      "batch.yaml",
+      "mode: same-sat\nsat: 5000111111\npostage: 300\ninscriptions:\n- file: inscription.txt\n",
+    )
+    .rpc_server(&rpc_server)
+    .expected_exit_code(1)
+    .stderr_regex(r"error: sat offset 0 is within \d+ sats of the end of the 300 sat inscription output; increase postage or drop --preserve-sat-offset\n")
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_warns_when_reveal_weight_is_near_limit() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new("wallet inscribe --file large.txt --fee-rate 1 --dry-run")
+    .write("large.txt", [0; 370_000])
+    .rpc_server(&rpc_server)
+    .stderr_regex(r".*warning: reveal transaction weight \d+ is above 90% of 400000 \(MAX_STANDARD_TX_WEIGHT\); consider splitting the batch\n.*")
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(output.warnings.len(), 1);
+  assert!(output.warnings[0].contains("consider splitting the batch"));
+}
+
+#[test]
+fn inscribe_with_reveal_parent_psbt_exports_and_finalizes_reveal() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let parent_id = CommandBuilder::new("wallet inscribe --fee-rate 1 --file parent.png")
+    .write("parent.png", [1; 520])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>()
+    .inscriptions[0]
+    .id;
+
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 1 --parent {parent_id} --file child.txt --reveal-parent-psbt"
+  ))
+  .write("child.txt", "CHILD")
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert!(rpc_server.mempool().is_empty());
+  assert!(output.commit_hex.is_some());
+
+  let mut psbt = output
+    .reveal_psbt
+    .unwrap()
+    .parse::<bitcoin::psbt::PartiallySignedTransaction>()
+    .unwrap();
+
+  assert_eq!(psbt.inputs.len(), 2);
+  assert!(psbt.inputs[0].final_script_witness.is_none());
+  assert!(psbt.inputs[0].witness_utxo.is_some());
+  assert!(psbt.inputs[1].final_script_witness.is_some());
+  assert!(psbt.inputs[1].witness_utxo.is_some());
+  assert_eq!(psbt.unsigned_tx.txid(), output.reveal.unwrap());
+
+  let signature = bitcoin::taproot::Signature {
+    sig: bitcoin::secp256k1::schnorr::Signature::from_slice(&[1; 64]).unwrap(),
+    hash_ty: bitcoin::sighash::TapSighashType::Default,
+  };
+
+  psbt.inputs[0].tap_key_sig = Some(signature);
+
+  let finalized = CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 1 --finalize-reveal-psbt {psbt}"
+  ))
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(finalized.reveal, output.reveal);
+
+  let reveal_tx = &rpc_server.mempool()[0];
+  assert_eq!(reveal_tx.txid(), output.reveal.unwrap());
+  assert_eq!(reveal_tx.input[0].witness.to_vec(), vec![signature.to_vec()]);
+  assert_eq!(reveal_tx.input[1].witness.len(), 3);
+}
+
+#[test]
+fn inscribe_estimate_reports_fees_without_transactions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new("wallet inscribe --file foo.txt --fee-rate 2 --estimate")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert!(rpc_server.mempool().is_empty());
+
+  let commit_fee = output.commit_fee.unwrap();
+  let reveal_fee = output.reveal_fee.unwrap();
+
+  assert!(commit_fee > 0);
+  assert!(reveal_fee > 0);
+  assert_eq!(output.total_fees, commit_fee + reveal_fee);
+  assert_eq!(reveal_fee, 2 * output.reveal_vsize.unwrap());
+  assert!(output.commit_address.is_some());
+
+  assert_eq!(output.commit, None);
+  assert_eq!(output.reveal, None);
+  assert_eq!(output.commit_hex, None);
+  assert_eq!(output.reveal_hex, None);
+  assert!(output.inscriptions.is_empty());
+}
+
+#[test]
+fn inscribe_estimate_without_wallet_sizes_commit_inputs_by_script_type() {
+  use bitcoin::{absolute::LockTime, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let p2wpkh = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+  let p2tr = "bc1pxwww0ct9ue7e8tdnlmug5m2tamfn7q06sahstg39ys4c9f3340qqxrdu9k";
+
+  CommandBuilder::new("wallet inscribe --fee-rate 1 --batch batch.yaml")
+    .write("foo.txt", "FOO")
+    .write(
+      "batch.yaml",
+      format!("mode: separate-outputs\ninscriptions:\n- file: foo.txt\noutputs:\n- address: {p2wpkh}\n  value: 20000\n- address: {p2tr}\n  value: 10000\n"),
+    )
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  let block = rpc_server.mine_blocks(1).remove(0);
+
+  let outpoint = |value| {
+    block
+      .txdata
+      .iter()
+      .find_map(|tx| {
+        tx.output
+          .iter()
+          .position(|output| output.value == value)
+          .map(|vout| OutPoint::new(tx.txid(), vout.try_into().unwrap()))
+      })
+      .unwrap()
+  };
+
+  let output = CommandBuilder::new("wallet inscribe --no-wallet --estimate --batch batch.yaml --fee-rate 0")
+    .write("foo.txt", "FOO")
+    .write(
+      "batch.yaml",
+      format!(
+        "mode: separate-outputs\nfees:\n- {}\ninscriptions:\n- file: foo.txt\n  utxo: {}\n  destination: {p2wpkh}\n",
+        outpoint(10_000),
+        outpoint(20_000),
+      ),
+    )
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  // a P2WPKH inscription input and a P2TR fee input, paying the commit address
+  let commit_vsize = Transaction {
+    version: 2,
+    lock_time: LockTime::ZERO,
+    input: vec![
+      TxIn {
+        previous_output: OutPoint::null(),
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+        witness: Witness::from_slice(&[[0; 72].as_slice(), &[0; 33]]),
+      },
+      TxIn {
+        previous_output: OutPoint::null(),
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+        witness: Witness::from_slice(&[&[0; 64]]),
+      },
+    ],
+    output: vec![TxOut {
+      value: 0,
+      script_pubkey: Address::from_str(p2tr)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey(),
+    }],
+  }
+  .vsize() as u64;
+
+  let reveal_vsize = output.reveal_vsize.unwrap();
+  let total_vsize = commit_vsize + reveal_vsize;
+
+  // the fee utxo is split between commit and reveal by their vsizes
+  assert_eq!(
+    output.reveal_fee.unwrap(),
+    (10_000 * reveal_vsize + total_vsize - 1) / total_vsize,
+  );
+  assert_eq!(
+    output.commit_fee.unwrap() + output.reveal_fee.unwrap(),
+    10_000
+  );
+  assert!(output.commit_psbt.is_none());
+}
+
+#[test]
+fn inscribe_psbt_package_round_trips_commit_and_signed_reveal() {
+  use bitcoin::{
+    psbt::PartiallySignedTransaction,
+    secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey},
+    sighash::{Prevouts, SighashCache, TapSighashType},
+    taproot::{LeafVersion, TapLeafHash},
+    ScriptBuf,
+  };
+
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new("wallet inscribe --fee-rate 1 --file foo.txt --psbt-package")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .stderr_regex(
+      ".*warning: commit input .* isn't segwit; signing it will change the commit txid and invalidate the reveal\n.*",
+    )
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert!(rpc_server.mempool().is_empty());
+  assert_eq!(output.psbt_package.len(), 2);
+
+  let commit_psbt = output.psbt_package[0]
+    .parse::<PartiallySignedTransaction>()
+    .unwrap();
+  let reveal_psbt = output.psbt_package[1]
+    .parse::<PartiallySignedTransaction>()
+    .unwrap();
+
+  assert_eq!(
+    commit_psbt.to_string().parse::<PartiallySignedTransaction>().unwrap(),
+    commit_psbt
+  );
+  assert_eq!(
+    reveal_psbt.to_string().parse::<PartiallySignedTransaction>().unwrap(),
+    reveal_psbt
+  );
+
+  assert_eq!(commit_psbt.unsigned_tx.txid(), output.commit.unwrap());
+  assert_eq!(reveal_psbt.unsigned_tx.txid(), output.reveal.unwrap());
+  assert!(commit_psbt
+    .inputs
+    .iter()
+    .all(|input| input.witness_utxo.is_some() && input.final_script_witness.is_none()));
+
+  let commit_input = reveal_psbt.unsigned_tx.input[0].previous_output;
+  assert_eq!(commit_input.txid, commit_psbt.unsigned_tx.txid());
+  assert_eq!(
+    reveal_psbt.inputs[0].witness_utxo.as_ref().unwrap(),
+    &commit_psbt.unsigned_tx.output[usize::try_from(commit_input.vout).unwrap()]
+  );
+
+  let witness = reveal_psbt.inputs[0]
+    .final_script_witness
+    .as_ref()
+    .unwrap()
+    .to_vec();
+  assert_eq!(witness.len(), 3);
+
+  let signature = schnorr::Signature::from_slice(&witness[0]).unwrap();
+  let script = ScriptBuf::from_bytes(witness[1].clone());
+  let public_key = XOnlyPublicKey::from_slice(&script.as_bytes()[1..33]).unwrap();
+
+  let prevouts = reveal_psbt
+    .inputs
+    .iter()
+    .map(|input| input.witness_utxo.clone().unwrap())
+    .collect::<Vec<_>>();
+
+  let sighash = SighashCache::new(&reveal_psbt.unsigned_tx)
+    .taproot_script_spend_signature_hash(
+      0,
+      &Prevouts::All(&prevouts),
+      TapLeafHash::from_script(&script, LeafVersion::TapScript),
+      TapSighashType::Default,
+    )
+    .unwrap();
+
+  Secp256k1::verification_only()
+    .verify_schnorr(
+      &signature,
+      &Message::from_slice(sighash.as_ref()).unwrap(),
+      &public_key,
+    )
+    .unwrap();
+}
+
+#[test]
+fn handoff_is_only_written_once_the_commit_is_broadcast() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let key = wif_key(1);
+
+  let tempdir = Arc::new(TempDir::new().unwrap());
+
+  for flag in ["--dry-run", "--estimate"] {
+    CommandBuilder::new(format!(
+      "wallet inscribe --commit-only {flag} --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+    ))
+    .temp_dir(tempdir.clone())
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .stdout_regex(".*")
+    .run_and_extract_stdout();
+
+    assert!(!tempdir.path().join("handoff.json").exists());
+  }
+
+  assert_eq!(rpc_server.mempool().len(), 0);
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commit-only --no-broadcast --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .expected_stderr(
+    "error: --handoff is written once the commit is broadcast, so it doesn't work with --no-broadcast or --no-wallet\n",
+  )
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commit-only --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .temp_dir(tempdir.clone())
+  .rpc_server(&rpc_server)
+  .stdout_regex(".*")
+  .run_and_extract_stdout();
+
+  assert!(tempdir.path().join("handoff.json").exists());
+  assert_eq!(rpc_server.mempool().len(), 1);
+}
+
+#[test]
+fn commitment_with_mismatched_handoff_is_rejected() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let key = wif_key(1);
+
+  let other_key = wif_key(2);
+
+  let handoff = CommandBuilder::new(format!(
+    "wallet inscribe --commit-only --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .rpc_server(&rpc_server)
+  .stdout_regex(".*")
+  .run_and_extract_file("handoff.json");
+
+  let parsed = serde_json::from_str::<ord::subcommand::wallet::inscribe::Handoff>(&handoff).unwrap();
+
+  assert_eq!(parsed.key, key);
+  assert_eq!(parsed.content_hashes.len(), 1);
+  assert_eq!(parsed.postage, 10_000);
+
+  let commit_address = parsed.commit_address.assume_checked();
+
+  let commit_tx = &rpc_server.mempool()[0];
+
+  let commitment = OutPoint {
+    txid: commit_tx.txid(),
+    vout: commit_tx
+      .output
+      .iter()
+      .position(|output| output.script_pubkey == commit_address.script_pubkey())
+      .unwrap()
+      .try_into()
+      .unwrap(),
+  };
+
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commitment {commitment} --key {other_key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .write("handoff.json", &handoff)
+  .rpc_server(&rpc_server)
+  .expected_exit_code(1)
+  .expected_stderr("error: --key doesn't match the key in handoff file `handoff.json`\n")
+  .run_and_extract_stdout();
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commitment {commitment} --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "BAR")
+  .write("handoff.json", &handoff)
+  .rpc_server(&rpc_server)
+  .expected_exit_code(1)
+  .expected_stderr("error: content of inscription 0 doesn't match handoff file `handoff.json`\n")
+  .run_and_extract_stdout();
+
+  assert!(rpc_server.mempool().is_empty());
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --commitment {commitment} --key {key} --handoff handoff.json --file foo.txt --fee-rate 1"
+  ))
+  .write("foo.txt", "FOO")
+  .write("handoff.json", &handoff)
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(rpc_server.mempool()[0].input[0].previous_output, commitment);
+}
+
+#[test]
+fn reveal_change_does_not_work_with_next_file() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(
+    "wallet inscribe --file foo.txt --next-file bar.txt --fee-rate 1 --reveal-change bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+  )
+  .write("foo.txt", "FOO")
+  .write("bar.txt", "BAR")
+  .rpc_server(&rpc_server)
+  .expected_stderr("error: --reveal-change doesn't work with --next-batch or --next-file\n")
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_to_specific_destination() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let destination = CommandBuilder::new("wallet receive")
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<ord::subcommand::wallet::receive::Output>()
+    .address;
+
+  let txid = CommandBuilder::new(format!(
+    "wallet inscribe --destination {} --file degenerate.png --fee-rate 1",
+    destination.clone().assume_checked()
+  ))
+  .write("degenerate.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>()
+  .reveal
+  .unwrap();
+
+  let reveal_tx = &rpc_server.mempool()[1]; // item 0 is the commit, item 1 is the reveal.
+  assert_eq!(reveal_tx.txid(), txid);
+  assert_eq!(
+    reveal_tx.output.first().unwrap().script_pubkey,
+    destination.payload.script_pubkey()
+  );
+}
+
+#[test]
+fn inscribe_to_address_on_different_network() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(
+    "wallet inscribe --destination tb1qsgx55dp6gn53tsmyjjv4c2ye403hgxynxs0dnm --file degenerate.png --fee-rate 1"
+  )
+  .write("degenerate.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .expected_exit_code(1)
+  .stderr_regex("error: address tb1qsgx55dp6gn53tsmyjjv4c2ye403hgxynxs0dnm belongs to network testnet which is different from required bitcoin\n")
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_with_no_limit() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let four_megger = std::iter::repeat(0).take(4_000_000).collect::<Vec<u8>>();
+  CommandBuilder::new("wallet inscribe --no-limit degenerate.png --fee-rate 1")
+    .write("degenerate.png", four_megger)
+    .rpc_server(&rpc_server);
+}
+
+#[test]
+fn inscribe_works_with_postage() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new("wallet inscribe --file foo.txt --postage 5btc --fee-rate 10".to_string())
+    .write("foo.txt", [0; 350])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  rpc_server.mine_blocks(1);
+
+  let inscriptions = CommandBuilder::new("wallet inscriptions".to_string())
+    .write("foo.txt", [0; 350])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Vec<ord::subcommand::wallet::inscriptions::Output>>();
+
+  pretty_assert_eq!(inscriptions[0].postage, 5 * COIN_VALUE);
+}
+
+#[test]
+fn inscribe_with_non_existent_parent_inscription() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let parent_id = "0000000000000000000000000000000000000000000000000000000000000000i0";
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 1.0 --parent {parent_id} --file child.png"
+  ))
+  .write("child.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .expected_stderr(format!("error: parent {parent_id} does not exist\n"))
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn inscribe_with_parent_inscription_and_fee_rate() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let parent_output = CommandBuilder::new("wallet inscribe --fee-rate 5.0 --file parent.png")
+    .write("parent.png", [1; 520])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(rpc_server.descriptors().len(), 3);
+  let parent_id = parent_output.inscriptions[0].id;
+
+  let commit_tx = &rpc_server.mempool()[0];
+  let reveal_tx = &rpc_server.mempool()[1];
+
+  assert_eq!(
+    ord::FeeRate::try_from(5.0)
+      .unwrap()
+      .fee(commit_tx.vsize() + reveal_tx.vsize())
+      .to_sat(),
+    parent_output.total_fees
+  );
+
+  rpc_server.mine_blocks(1);
+
+  let child_output = CommandBuilder::new(format!(
+    "wallet inscribe --fee-rate 7.3 --parent {parent_id} --file child.png"
+  ))
+  .write("child.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(rpc_server.descriptors().len(), 4);
+  assert_eq!(parent_id, child_output.parent.unwrap());
+
+  let commit_tx = &rpc_server.mempool()[0];
+  let reveal_tx = &rpc_server.mempool()[1];
+
+  assert_eq!(
+    ord::FeeRate::try_from(7.3)
+      .unwrap()
+      .fee(commit_tx.vsize() + reveal_tx.vsize())
+      .to_sat(),
+    child_output.total_fees
+  );
+
+  rpc_server.mine_blocks(1);
+
+  let ord_server = TestServer::spawn_with_args(&rpc_server, &[]);
+
+  ord_server.assert_response_regex(
+    format!("/inscription/{}", child_output.parent.unwrap()),
+    format!(
+      ".*<dt>children</dt>.*<a href=/inscription/{}>.*",
+      child_output.inscriptions[0].id
+    ),
+  );
+
+  ord_server.assert_response_regex(
+    format!("/inscription/{}", child_output.inscriptions[0].id),
+    format!(
+      ".*<dt>parent</dt>.*<a class=monospace href=/inscription/{}>.*",
+      child_output.parent.unwrap()
+    ),
+  );
+}
+
+#[test]
+fn reinscribe_with_flag() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  rpc_server.mine_blocks(1);
+
+  assert_eq!(rpc_server.descriptors().len(), 0);
+
+  create_wallet(&rpc_server);
+
+  let inscribe = CommandBuilder::new("wallet inscribe --file tulip.png --fee-rate 5.0 ")
+    .write("tulip.png", [1; 520])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  assert_eq!(rpc_server.descriptors().len(), 3);
+
+  let txid = rpc_server.mine_blocks(1)[0].txdata[2].txid();
+
+  let ord_server = TestServer::spawn_with_args(&rpc_server, &[]);
+  let request = ord_server.request(format!("/content/{}", inscribe.inscriptions[0].id));
+
+  assert_eq!(request.status(), 200);
+
+  let reinscribe = CommandBuilder::new(format!(
+    "wallet inscribe --file orchid.png --fee-rate 1.1 --reinscribe --satpoint {txid}:0:0"
+  ))
+  .write("orchid.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .run_and_deserialize_output::<Inscribe>();
+
+  rpc_server.mine_blocks(1);
+
+  let ord_server = TestServer::spawn_with_args(&rpc_server, &["--index-sats"]);
+  let request = ord_server.request(format!("/content/{}", reinscribe.inscriptions[0].id));
+
+  assert_eq!(request.status(), 200);
+  ord_server.assert_response_regex(
+    format!("/sat/{}", 50 * COIN_VALUE),
+    format!(
+      ".*<dt>inscriptions</dt>.*<a href=/inscription/{}>.*<a href=/inscription/{}>.*",
+      inscribe.inscriptions[0].id, reinscribe.inscriptions[0].id
+    ),
+  );
+}
+
+#[test]
+fn with_reinscribe_flag_but_not_actually_a_reinscription() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  rpc_server.mine_blocks(1);
+
+  assert_eq!(rpc_server.descriptors().len(), 0);
+
+  create_wallet(&rpc_server);
+
+  CommandBuilder::new("wallet inscribe --file tulip.png --fee-rate 5.0 ")
+    .write("tulip.png", [1; 520])
+    .rpc_server(&rpc_server)
+    .run_and_deserialize_output::<Inscribe>();
+
+  let coinbase = rpc_server.mine_blocks(1)[0].txdata[0].txid();
+
+  CommandBuilder::new(format!(
+    "wallet inscribe --file orchid.png --fee-rate 1.1 --reinscribe --satpoint {coinbase}:0:0"
+  ))
+  .write("orchid.png", [1; 520])
+  .rpc_server(&rpc_server)
+  .expected_exit_code(1)
+  .stderr_regex("error: reinscribe flag set but this would not be a reinscription.*")
+  .run_and_extract_stdout();
+}
+
+#[test]
+fn try_reinscribe_without_flag() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  rpc_server.mine_blocks(1);
+
+  assert_eq!(rpc_server.descriptors().len(), 0);
+
   create_wallet(&rpc_server);
 
   let reveal_txid = CommandBuilder::new("wallet inscribe --file tulip.png --fee-rate 5.0 ")
     .write("tulip.png", [1; 520])
     .rpc_server(&rpc_server)
     .run_and_deserialize_output::<Inscribe>()
-    .reveal;
+    .reveal
+    .unwrap();
 
   assert_eq!(rpc_server.descriptors().len(), 3);
 